@@ -0,0 +1,292 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, NaiveDateTime, UTC};
+
+use result::{Result, Error, ErrorKind, with_source};
+
+/// The seconds between the Unix epoch and the reference date of 2001-01-01.
+const UNIX_TO_REFERENCE: i64 = 978307200;
+
+/// A date value, stored as the seconds since 2001-01-01 00:00:00 UTC.
+///
+/// This is the `CFAbsoluteTime` that binary property lists store, so dates
+/// keep their full precision when read and written in any format.
+///
+/// Dates are totally ordered, so that they can be compared and hashed like
+/// any other value. Zero and negative zero are the same date, and NaN dates
+/// are equal to each other and ordered after every other date, or before if
+/// negative.
+#[derive(Debug, Clone, Copy)]
+pub struct Date {
+    absolute_time: f64,
+}
+
+impl Date {
+    /// Creates a date from the seconds since 2001-01-01 00:00:00 UTC.
+    pub fn from_absolute_time(absolute_time: f64) -> Date {
+        Date { absolute_time: absolute_time }
+    }
+
+    /// Returns the seconds since 2001-01-01 00:00:00 UTC.
+    pub fn absolute_time(&self) -> f64 {
+        self.absolute_time
+    }
+
+    /// Parses a date in the RFC 3339 format used by XML property lists.
+    ///
+    /// Fractional seconds are read to their full precision, rather than only
+    /// to the nanosecond.
+    pub fn from_rfc3339(string: &str) -> Result<Date> {
+        let date = try!(DateTime::parse_from_rfc3339(string)
+            .map_err(|e| with_source(ErrorKind::InvalidDate, e)));
+
+        // The seconds are always followed by the fraction, if there is one
+        let fraction = match string.find('.') {
+            Some(i) => string[i + 1..].split(|c: char| !c.is_digit(10)).next().unwrap_or(""),
+            None => "",
+        };
+
+        // The whole seconds are rounded down, so the fraction of a date before
+        // the reference date counts towards it
+        let secs = date.timestamp() - UNIX_TO_REFERENCE;
+        let time = if secs < 0 && fraction.bytes().any(|b| b != b'0') {
+            format!("-{}.{}", -secs - 1, complement(fraction))
+        } else {
+            format!("{}.{}", secs, fraction)
+        };
+        Ok(Date::from_absolute_time(try!(time.parse())))
+    }
+
+    /// Formats the date in the RFC 3339 format, in UTC, including fractional
+    /// seconds only when they are present.
+    ///
+    /// The fraction has 3, 6 or 9 digits, or as many more as are needed to
+    /// read back the same date.
+    ///
+    /// Fails with `ErrorKind::DateOutOfRange` if the year cannot be formatted.
+    pub fn to_rfc3339(&self) -> Result<String> {
+        let time = self.absolute_time;
+        if !time.is_finite() || time.abs() > 1e15 {
+            return Err(ErrorKind::DateOutOfRange.into());
+        }
+
+        // Formatting a float gives the fewest digits that parse back to it
+        let formatted = time.abs().to_string();
+        let mut parts = formatted.splitn(2, '.');
+        let whole: i64 = try!(parts.next().unwrap_or("0").parse());
+        let mut fraction = parts.next().unwrap_or("").to_string();
+        let len = match fraction.len() {
+            len @ 1..=9 => (len + 2) / 3 * 3,
+            len => len,
+        };
+        while fraction.len() < len {
+            fraction.push('0');
+        }
+
+        let (secs, fraction) = if time < 0.0 && !fraction.is_empty() {
+            (-whole - 1, complement(&fraction))
+        } else if time < 0.0 {
+            (-whole, fraction)
+        } else {
+            (whole, fraction)
+        };
+
+        let date = try!(NaiveDateTime::from_timestamp_opt(secs + UNIX_TO_REFERENCE, 0)
+            .ok_or(ErrorKind::DateOutOfRange));
+        let mut string = date.format("%Y-%m-%dT%H:%M:%S").to_string();
+        if !fraction.is_empty() {
+            string.push('.');
+            string.push_str(&fraction);
+        }
+        string.push('Z');
+        Ok(string)
+    }
+
+    fn from_timestamp(secs: i64, nanos: u32) -> Date {
+        Date::from_absolute_time((secs - UNIX_TO_REFERENCE) as f64 + nanos as f64 / 1e9)
+    }
+
+    // Orders the dates as `f64::total_cmp` does, after adding zero to turn
+    // negative zero into zero
+    fn key(&self) -> i64 {
+        let bits = (self.absolute_time + 0.0).to_bits() as i64;
+        bits ^ ((bits >> 63) as u64 >> 1) as i64
+    }
+}
+
+impl PartialEq for Date {
+    fn eq(&self, other: &Date) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Date {}
+
+impl PartialOrd for Date {
+    fn partial_cmp(&self, other: &Date) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Date {
+    fn cmp(&self, other: &Date) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for Date {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+// The digits of one minus a decimal fraction, which must not be zero
+fn complement(fraction: &str) -> String {
+    let mut digits: Vec<u8> = fraction.bytes().collect();
+    let mut borrow = false;
+    for digit in digits.iter_mut().rev() {
+        if borrow {
+            *digit = b'9' - (*digit - b'0');
+        } else if *digit != b'0' {
+            *digit = b'0' + 10 - (*digit - b'0');
+            borrow = true;
+        }
+    }
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Returns the date as a `NaiveDateTime` in UTC, or `None` if it is out of
+/// range.
+///
+/// The fractional seconds are rounded to the fewest of 3, 6 or 9 digits that
+/// convert back to the same date.
+pub fn naive_date(date: &Date) -> Option<NaiveDateTime> {
+    let time = date.absolute_time;
+    if !time.is_finite() || time.abs() > 1e15 {
+        return None;
+    }
+
+    let secs = time.floor();
+    let fraction = time - secs;
+    let mut nanos = (fraction * 1e9).round() as u32;
+    for &(digits, scale) in &[(1e3, 1_000_000), (1e6, 1_000)] {
+        let rounded = (fraction * digits).round();
+        if secs + rounded / digits == time {
+            nanos = rounded as u32 * scale;
+            break;
+        }
+    }
+
+    // Rounding can carry into the next second
+    let secs = secs as i64 + UNIX_TO_REFERENCE + (nanos / 1_000_000_000) as i64;
+    NaiveDateTime::from_timestamp_opt(secs, nanos % 1_000_000_000)
+}
+
+impl From<SystemTime> for Date {
+    fn from(time: SystemTime) -> Date {
+        let reference = UNIX_EPOCH + Duration::from_secs(UNIX_TO_REFERENCE as u64);
+        let secs = |d: Duration| d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9;
+        Date::from_absolute_time(match time.duration_since(reference) {
+            Ok(d) => secs(d),
+            Err(e) => -secs(e.duration()),
+        })
+    }
+}
+
+impl TryFrom<Date> for SystemTime {
+    type Error = Error;
+
+    fn try_from(date: Date) -> Result<SystemTime> {
+        let time = date.absolute_time;
+        if !time.is_finite() || time.abs() >= u64::max_value() as f64 {
+            return Err(ErrorKind::DateOutOfRange.into());
+        }
+
+        let reference = UNIX_EPOCH + Duration::from_secs(UNIX_TO_REFERENCE as u64);
+        // Rounding the nanoseconds can carry into the next second
+        let duration = Duration::new(time.abs().trunc() as u64,
+                                     (time.abs().fract() * 1e9).round() as u32);
+        let result = if time < 0.0 {
+            reference.checked_sub(duration)
+        } else {
+            reference.checked_add(duration)
+        };
+        result.ok_or(ErrorKind::DateOutOfRange.into())
+    }
+}
+
+impl From<DateTime<UTC>> for Date {
+    fn from(date: DateTime<UTC>) -> Date {
+        Date::from_timestamp(date.timestamp(), date.timestamp_subsec_nanos())
+    }
+}
+
+impl TryFrom<Date> for DateTime<UTC> {
+    type Error = Error;
+
+    fn try_from(date: Date) -> Result<DateTime<UTC>> {
+        let date = try!(naive_date(&date).ok_or(ErrorKind::DateOutOfRange));
+        Ok(DateTime::from_utc(date, UTC))
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_rfc3339() {
+            Ok(s) => f.write_str(&s),
+            Err(_) => write!(f, "{} seconds since 2001-01-01T00:00:00Z", self.absolute_time),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::fmt;
+    use serde::de::{self, Deserialize, Deserializer, Visitor};
+    use serde::ser::{self, Serialize, Serializer};
+
+    use super::Date;
+
+    /// The newtype struct name the plist serializer and deserializer use to
+    /// recognize dates.
+    pub const DATE_NEWTYPE_STRUCT_NAME: &'static str = "PLIST-DATE";
+
+    impl Serialize for Date {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let date = try!(self.to_rfc3339().map_err(ser::Error::custom));
+            serializer.serialize_newtype_struct(DATE_NEWTYPE_STRUCT_NAME, &date)
+        }
+    }
+
+    struct DateVisitor;
+
+    impl<'de> Visitor<'de> for DateVisitor {
+        type Value = Date;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an RFC 3339 date")
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(self,
+                                                      deserializer: D)
+                                                      -> Result<Date, D::Error> {
+            deserializer.deserialize_str(self)
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Date, E> {
+            Date::from_rfc3339(v).map_err(E::custom)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Date {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Date, D::Error> {
+            deserializer.deserialize_newtype_struct(DATE_NEWTYPE_STRUCT_NAME, DateVisitor)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use self::serde_impls::DATE_NEWTYPE_STRUCT_NAME;
@@ -0,0 +1,256 @@
+//! Deserialization of Rust types from property lists using serde.
+//!
+//! # Examples
+//!
+//! ```rust
+//! extern crate plist;
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! use std::fs::File;
+//!
+//! #[derive(Deserialize)]
+//! struct Types {
+//!     #[serde(rename = "String")]
+//!     string: String,
+//!     #[serde(rename = "Integer")]
+//!     integer: i64,
+//! }
+//!
+//! fn main() {
+//!     let mut f = File::open("tests/types-binary.plist").unwrap();
+//!     let types: Types = plist::de::from_reader(&mut f).unwrap();
+//!     assert_eq!(types.string, "Hello");
+//!     assert_eq!(types.integer, 5);
+//! }
+//! ```
+
+use std::io::{Read, Seek};
+use std::vec;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use date::DATE_NEWTYPE_STRUCT_NAME;
+use plist::{Plist, Dictionary};
+use result::{Result, Error};
+
+/// A serde deserializer that reads Rust values from a `Plist`.
+#[derive(Debug)]
+pub struct Deserializer {
+    value: Plist,
+}
+
+impl Deserializer {
+    /// Creates a deserializer over a property list value.
+    pub fn new(value: Plist) -> Deserializer {
+        Deserializer { value: value }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Plist::Array(array) => {
+                let len = array.len();
+                let mut seq = SeqDeserializer { iter: array.into_iter() };
+                let value = try!(visitor.visit_seq(&mut seq));
+                expect_end(len, seq.iter.len()).map(|_| value)
+            }
+            Plist::Dict(dict) => {
+                let len = dict.len();
+                let mut map = MapDeserializer {
+                    iter: dict.into_iter(),
+                    value: None,
+                };
+                let value = try!(visitor.visit_map(&mut map));
+                expect_end(len, map.iter.len()).map(|_| value)
+            }
+            Plist::Boolean(b) => visitor.visit_bool(b),
+            Plist::Data(d) => visitor.visit_byte_buf(d),
+            Plist::DateTime(d) => visitor.visit_string(try!(d.to_rfc3339())),
+            Plist::Real(r) => visitor.visit_f64(r),
+            Plist::Integer(i) => {
+                match i.as_signed() {
+                    Some(i) => visitor.visit_i64(i),
+                    None => visitor.visit_u64(i.as_unsigned().unwrap_or(0)),
+                }
+            }
+            Plist::String(s) => visitor.visit_string(s),
+            Plist::Uid(u) => visitor.visit_u64(u),
+            Plist::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Plist::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self,
+                                                   name: &'static str,
+                                                   visitor: V)
+                                                   -> Result<V::Value> {
+        match self.value {
+            Plist::DateTime(_) if name == DATE_NEWTYPE_STRUCT_NAME => self.deserialize_any(visitor),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self,
+                                         _name: &'static str,
+                                         _variants: &'static [&'static str],
+                                         visitor: V)
+                                         -> Result<V::Value> {
+        match self.value {
+            Plist::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Plist::Dict(dict) => {
+                let len = dict.len();
+                let mut iter = dict.into_iter();
+                match (iter.next(), iter.next()) {
+                    (Some((variant, value)), None) => {
+                        visitor.visit_enum(EnumDeserializer {
+                            variant: variant,
+                            value: value,
+                        })
+                    }
+                    _ => Err(de::Error::invalid_length(len, &"a dictionary of one key")),
+                }
+            }
+            _ => Err(de::Error::custom("An enum must be a string or a dictionary")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Plist {
+    type Deserializer = Deserializer;
+
+    fn into_deserializer(self) -> Deserializer {
+        Deserializer::new(self)
+    }
+}
+
+#[inline]
+fn expect_end(len: usize, remaining: usize) -> Result<()> {
+    if remaining == 0 {
+        Ok(())
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer elements"))
+    }
+}
+
+struct SeqDeserializer {
+    iter: vec::IntoIter<Plist>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for &'a mut SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(Deserializer::new(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: <Dictionary as IntoIterator>::IntoIter,
+    value: Option<Plist>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for &'a mut MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(Deserializer::new(Plist::String(key))).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(Deserializer::new(value)),
+            None => Err(de::Error::custom("A map value was requested before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Plist,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Deserializer)> {
+        let variant = try!(seed.deserialize(Deserializer::new(Plist::String(self.variant))));
+        Ok((variant, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Err(de::Error::custom("A unit variant must be a string"))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self,
+                                       _fields: &'static [&'static str],
+                                       visitor: V)
+                                       -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserializes an instance of type `T` from a property list value.
+pub fn from_plist<T: DeserializeOwned>(plist: Plist) -> Result<T> {
+    T::deserialize(Deserializer::new(plist))
+}
+
+/// Deserializes an instance of type `T` from a binary property list.
+pub fn from_binary_reader<R: Read + Seek, T: DeserializeOwned>(input: &mut R) -> Result<T> {
+    from_plist(try!(Plist::from_binary_reader(input)))
+}
+
+/// Deserializes an instance of type `T` from an XML property list.
+pub fn from_xml_reader<R: Read, T: DeserializeOwned>(input: &mut R) -> Result<T> {
+    from_plist(try!(Plist::from_xml_reader(input)))
+}
+
+/// Deserializes an instance of type `T` from a binary, XML or ASCII property
+/// list, detecting the format as `Plist::from_reader` does.
+pub fn from_reader<R: Read + Seek, T: DeserializeOwned>(input: &mut R) -> Result<T> {
+    from_plist(try!(Plist::from_reader(input)))
+}
+
@@ -0,0 +1,50 @@
+use std::fmt;
+
+/// An integer value, which can hold any `i64` or `u64`.
+///
+/// Binary property lists store integers above `i64::MAX` in 16 bytes, so
+/// neither `i64` nor `u64` alone covers every integer a property list holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Integer {
+    value: i128,
+}
+
+impl Integer {
+    /// Returns the value as an `i64`, if it is in range.
+    pub fn as_signed(&self) -> Option<i64> {
+        if self.value >= i64::min_value() as i128 && self.value <= i64::max_value() as i128 {
+            Some(self.value as i64)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value as a `u64`, if it is in range.
+    pub fn as_unsigned(&self) -> Option<u64> {
+        if self.value >= 0 && self.value <= u64::max_value() as i128 {
+            Some(self.value as u64)
+        } else {
+            None
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($t:ty)*) => {
+        $(
+            impl From<$t> for Integer {
+                fn from(value: $t) -> Integer {
+                    Integer { value: value as i128 }
+                }
+            }
+        )*
+    }
+}
+
+impl_from!(i8 i16 i32 i64 u8 u16 u32 u64);
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
@@ -0,0 +1,641 @@
+//! Decoding and encoding of `NSKeyedArchiver` archives
+//!
+//! An archive is a property list whose `$objects` array holds every archived
+//! object, with references between objects stored as UIDs. `KeyedArchive`
+//! resolves the objects into typed nodes, which refer to each other through
+//! `ObjectRef` indices so that shared and cyclic references are preserved.
+//! `KeyedArchiver` builds an archive from the same nodes.
+//!
+//! ```rust
+//! extern crate plist;
+//!
+//! use plist::Plist;
+//! use plist::keyed_archive::{ArchivedObject, KeyedArchive};
+//!
+//! fn main() {
+//!     let plist = Plist::from_reader(&mut std::io::Cursor::new(&b"{
+//!         $archiver = NSKeyedArchiver;
+//!         $top = { root = { CF$UID = 1; }; };
+//!         $objects = ( $null, Hello );
+//!     }"[..])).unwrap();
+//!     let archive = KeyedArchive::from_plist(plist).unwrap();
+//!     match *archive.object(archive.root().unwrap()).unwrap() {
+//!         ArchivedObject::String(ref s) => assert_eq!(s, "Hello"),
+//!         ref o => panic!("Unexpected object {:?}", o),
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::io::{Read, Seek};
+use fnv::FnvHasher;
+use indexmap::IndexMap;
+
+use date::Date;
+use integer::Integer;
+use plist::{Plist, Dictionary};
+use result::{Result, Error, ErrorKind};
+
+/// A map of names to values, kept in the order they appear in the archive.
+pub type Map<V> = IndexMap<String, V, BuildHasherDefault<FnvHasher>>;
+
+/// A reference to an object in a `KeyedArchive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectRef(usize);
+
+impl ObjectRef {
+    /// Returns the index of the object in the archive's `$objects` array.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// The class of an archived object.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Class {
+    /// The name of the class
+    pub name: String,
+    /// The names of the class and its superclasses, starting with the class
+    pub hierarchy: Vec<String>,
+}
+
+/// A field of an archived object of an unknown class.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    /// A reference to another object
+    Object(ObjectRef),
+    /// A value stored inline, such as an integer or a boolean
+    Value(Plist),
+}
+
+/// A numeric value, as stored for an `NSNumber`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    /// A boolean value
+    Boolean(bool),
+    /// An integer value
+    Integer(Integer),
+    /// A floating point value
+    Real(f64),
+}
+
+/// An object decoded from a `KeyedArchive`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchivedObject {
+    /// The `$null` placeholder, which stands for a nil reference
+    Nil,
+    /// An `NSNull`
+    Null,
+    /// An `NSString`
+    String(String),
+    /// An `NSNumber`
+    Number(Number),
+    /// An `NSData`
+    Data(Vec<u8>),
+    /// An `NSDate`
+    Date(Date),
+    /// An `NSArray`
+    Array(Vec<ObjectRef>),
+    /// An `NSSet`
+    Set(Vec<ObjectRef>),
+    /// An `NSDictionary`, as its key and value pairs
+    Dictionary(Vec<(ObjectRef, ObjectRef)>),
+    /// An `NSURL`, with the URL it is relative to, if any
+    Url {
+        /// The URL the relative string is resolved against
+        base: Option<ObjectRef>,
+        /// The string of the URL
+        relative: String,
+    },
+    /// An `NSUUID`
+    Uuid([u8; 16]),
+    /// A class, which objects refer to through their `$class` field
+    Class(Class),
+    /// An object of any other class, with its encoded fields
+    Object {
+        /// The class of the object
+        class: Class,
+        /// The fields encoded by the object, excluding `$class`
+        fields: Map<Field>,
+    },
+}
+
+/// A decoded `NSKeyedArchiver` archive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyedArchive {
+    archiver: String,
+    version: i64,
+    top: Map<ObjectRef>,
+    objects: Vec<ArchivedObject>,
+}
+
+impl KeyedArchive {
+    /// Decodes an archive from a property list.
+    ///
+    /// References must be UIDs, so property lists built with `CF$UID`
+    /// dictionaries need to be passed through `resolve_uid_dicts` first.
+    pub fn from_plist(plist: Plist) -> Result<KeyedArchive> {
+        let mut root = match plist {
+            Plist::Dict(dict) => dict,
+            _ => return Err(invalid("the root object is not a dictionary")),
+        };
+
+        let archiver = match root.get("$archiver") {
+            Some(&Plist::String(ref s)) if s == "NSKeyedArchiver" => s.clone(),
+            _ => return Err(invalid("the archiver is not NSKeyedArchiver")),
+        };
+        let version = match root.get("$version") {
+            Some(&Plist::Integer(v)) => v.as_signed().unwrap_or(0),
+            _ => 0,
+        };
+        let objects = match root.swap_remove("$objects") {
+            Some(Plist::Array(objects)) => objects,
+            _ => return Err(invalid("$objects is not an array")),
+        };
+
+        let mut top = Map::default();
+        match root.get("$top") {
+            Some(&Plist::Dict(ref dict)) => {
+                for (key, value) in dict {
+                    top.insert(key.clone(), try!(object_ref(value, objects.len())));
+                }
+            }
+            _ => return Err(invalid("$top is not a dictionary")),
+        }
+
+        let decoded = try!(objects.iter()
+            .enumerate()
+            .map(|(i, o)| decode_object(i, o, &objects))
+            .collect());
+
+        Ok(KeyedArchive {
+            archiver: archiver,
+            version: version,
+            top: top,
+            objects: decoded,
+        })
+    }
+
+    /// Decodes an archive from a binary, XML or ASCII property list.
+    pub fn from_reader<R: Read + Seek>(input: &mut R) -> Result<KeyedArchive> {
+        KeyedArchive::from_plist(resolve_uid_dicts(try!(Plist::from_reader(input))))
+    }
+
+    /// Returns the name of the archiver, which is always `NSKeyedArchiver`.
+    pub fn archiver(&self) -> &str {
+        &self.archiver
+    }
+
+    /// Returns the version of the archive format.
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Returns the top level objects, keyed by the names they were encoded
+    /// with.
+    pub fn top(&self) -> &Map<ObjectRef> {
+        &self.top
+    }
+
+    /// Returns the top level object encoded as `root`, which is where
+    /// `+[NSKeyedArchiver archivedDataWithRootObject:]` stores its object.
+    pub fn root(&self) -> Option<ObjectRef> {
+        self.top.get("root").cloned()
+    }
+
+    /// Returns the object that a reference refers to, or `None` if the
+    /// reference is not to an object in this archive, such as one from a
+    /// `KeyedArchiver` or a larger archive.
+    pub fn object(&self, reference: ObjectRef) -> Option<&ArchivedObject> {
+        self.objects.get(reference.0)
+    }
+
+    /// Returns all of the objects in the archive, indexed by `ObjectRef`.
+    pub fn objects(&self) -> &[ArchivedObject] {
+        &self.objects
+    }
+}
+
+/// A builder for `NSKeyedArchiver` archives.
+///
+/// Objects are encoded one at a time, and can only refer to objects that were
+/// encoded or reserved before them. Equal strings and classes are stored
+/// once, and an object referenced more than once is only stored once.
+///
+/// ```rust
+/// extern crate plist;
+///
+/// use plist::keyed_archive::{ArchivedObject, KeyedArchiver};
+///
+/// fn main() {
+///     let mut archiver = KeyedArchiver::new();
+///     let hello = archiver.encode(ArchivedObject::String("Hello".to_string())).unwrap();
+///     let array = archiver.encode(ArchivedObject::Array(vec![hello, hello])).unwrap();
+///     archiver.set_top("root", array);
+///
+///     let mut buf = Vec::new();
+///     archiver.into_plist().unwrap().to_binary_writer(&mut buf).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyedArchiver {
+    objects: Vec<Option<Plist>>,
+    strings: HashMap<String, ObjectRef>,
+    classes: HashMap<Class, ObjectRef>,
+    top: Map<ObjectRef>,
+}
+
+impl KeyedArchiver {
+    /// Creates an archiver with no objects.
+    pub fn new() -> KeyedArchiver {
+        KeyedArchiver {
+            objects: vec![Some(Plist::String("$null".to_string()))],
+            strings: HashMap::new(),
+            classes: HashMap::new(),
+            top: Map::default(),
+        }
+    }
+
+    /// Returns the reference to the `$null` placeholder, which stands for nil.
+    pub fn nil(&self) -> ObjectRef {
+        ObjectRef(0)
+    }
+
+    /// Reserves a reference for an object that is encoded later with
+    /// `encode_reserved`, so that objects can refer to each other in a cycle.
+    pub fn reserve(&mut self) -> ObjectRef {
+        self.objects.push(None);
+        ObjectRef(self.objects.len() - 1)
+    }
+
+    /// Encodes an object, returning its reference.
+    ///
+    /// Fails with `ErrorKind::InvalidObjectReference` if the object refers to an
+    /// object that has not been encoded or reserved by this archiver.
+    pub fn encode(&mut self, object: ArchivedObject) -> Result<ObjectRef> {
+        match object {
+            ArchivedObject::Nil => return Ok(self.nil()),
+            ArchivedObject::String(s) => {
+                if let Some(&reference) = self.strings.get(&s) {
+                    return Ok(reference);
+                }
+                let reference = self.push(Plist::String(s.clone()));
+                self.strings.insert(s, reference);
+                return Ok(reference);
+            }
+            ArchivedObject::Class(class) => return Ok(self.class(class)),
+            _ => (),
+        }
+
+        let reference = self.reserve();
+        try!(self.encode_reserved(reference, object));
+        Ok(reference)
+    }
+
+    /// Encodes an object for a reference returned by `reserve`.
+    pub fn encode_reserved(&mut self, reference: ObjectRef, object: ArchivedObject) -> Result<()> {
+        match self.objects.get(reference.0) {
+            Some(&None) => (),
+            _ => return Err(ErrorKind::InvalidObjectReference(reference.0 as u64).into()),
+        }
+
+        let plist = match object {
+            // Nil is always the first object, so it cannot be reserved
+            ArchivedObject::Nil => return Err(invalid("nil cannot be encoded as an object")),
+            ArchivedObject::String(s) => Plist::String(s),
+            ArchivedObject::Class(class) => class_plist(&class),
+            ArchivedObject::Null => self.instance("NSNull", Vec::new()),
+            ArchivedObject::Number(Number::Boolean(b)) => Plist::Boolean(b),
+            ArchivedObject::Number(Number::Integer(i)) => Plist::Integer(i),
+            ArchivedObject::Number(Number::Real(r)) => Plist::Real(r),
+            ArchivedObject::Data(d) => Plist::Data(d),
+            ArchivedObject::Date(d) => {
+                self.instance("NSDate", vec![("NS.time", Plist::Real(d.absolute_time()))])
+            }
+            ArchivedObject::Array(objects) => {
+                let objects = try!(self.uids(&objects));
+                self.instance("NSArray", vec![("NS.objects", objects)])
+            }
+            ArchivedObject::Set(objects) => {
+                let objects = try!(self.uids(&objects));
+                self.instance("NSSet", vec![("NS.objects", objects)])
+            }
+            ArchivedObject::Dictionary(entries) => {
+                let keys: Vec<_> = entries.iter().map(|e| e.0).collect();
+                let values: Vec<_> = entries.iter().map(|e| e.1).collect();
+                let keys = try!(self.uids(&keys));
+                let values = try!(self.uids(&values));
+                self.instance("NSDictionary", vec![("NS.keys", keys), ("NS.objects", values)])
+            }
+            ArchivedObject::Url { base, relative } => {
+                let base = try!(self.uid(base.unwrap_or(ObjectRef(0))));
+                let relative = try!(self.encode(ArchivedObject::String(relative)));
+                let relative = try!(self.uid(relative));
+                self.instance("NSURL", vec![("NS.base", base), ("NS.relative", relative)])
+            }
+            ArchivedObject::Uuid(bytes) => {
+                self.instance("NSUUID", vec![("NS.uuidbytes", Plist::Data(bytes.to_vec()))])
+            }
+            ArchivedObject::Object { class, fields } => {
+                let mut dict = Dictionary::default();
+                dict.insert("$class".to_string(), Plist::Uid(self.class(class).0 as u64));
+                for (key, field) in fields {
+                    let value = match field {
+                        Field::Object(r) => try!(self.uid(r)),
+                        Field::Value(v) => v,
+                    };
+                    dict.insert(key, value);
+                }
+                Plist::Dict(dict)
+            }
+        };
+
+        self.objects[reference.0] = Some(plist);
+        Ok(())
+    }
+
+    /// Sets a top level object, such as `root`.
+    pub fn set_top(&mut self, key: &str, object: ObjectRef) {
+        self.top.insert(key.to_string(), object);
+    }
+
+    /// Builds the archive, which can be written in any property list format.
+    ///
+    /// Fails if an object was reserved but never encoded.
+    pub fn into_plist(self) -> Result<Plist> {
+        let mut objects = Vec::with_capacity(self.objects.len());
+        for (i, object) in self.objects.into_iter().enumerate() {
+            match object {
+                Some(object) => objects.push(object),
+                None => return Err(ErrorKind::InvalidObjectReference(i as u64).into()),
+            }
+        }
+
+        let mut top = Dictionary::default();
+        for (key, reference) in self.top {
+            if reference.0 >= objects.len() {
+                return Err(ErrorKind::InvalidObjectReference(reference.0 as u64).into());
+            }
+            top.insert(key, Plist::Uid(reference.0 as u64));
+        }
+
+        let mut archive = Dictionary::default();
+        archive.insert("$archiver".to_string(), Plist::String("NSKeyedArchiver".to_string()));
+        archive.insert("$objects".to_string(), Plist::Array(objects));
+        archive.insert("$top".to_string(), Plist::Dict(top));
+        archive.insert("$version".to_string(), Plist::Integer(100000.into()));
+        Ok(Plist::Dict(archive))
+    }
+
+    fn push(&mut self, plist: Plist) -> ObjectRef {
+        self.objects.push(Some(plist));
+        ObjectRef(self.objects.len() - 1)
+    }
+
+    fn uid(&self, reference: ObjectRef) -> Result<Plist> {
+        if reference.0 >= self.objects.len() {
+            return Err(ErrorKind::InvalidObjectReference(reference.0 as u64).into());
+        }
+        Ok(Plist::Uid(reference.0 as u64))
+    }
+
+    fn uids(&self, references: &[ObjectRef]) -> Result<Plist> {
+        let uids = try!(references.iter().map(|&r| self.uid(r)).collect());
+        Ok(Plist::Array(uids))
+    }
+
+    fn class(&mut self, class: Class) -> ObjectRef {
+        if let Some(&reference) = self.classes.get(&class) {
+            return reference;
+        }
+        let reference = self.push(class_plist(&class));
+        self.classes.insert(class, reference);
+        reference
+    }
+
+    fn instance(&mut self, name: &str, fields: Vec<(&str, Plist)>) -> Plist {
+        let class = Class {
+            name: name.to_string(),
+            hierarchy: vec![name.to_string(), "NSObject".to_string()],
+        };
+
+        let mut dict = Dictionary::default();
+        dict.insert("$class".to_string(), Plist::Uid(self.class(class).0 as u64));
+        for (key, value) in fields {
+            dict.insert(key.to_string(), value);
+        }
+        Plist::Dict(dict)
+    }
+}
+
+impl Default for KeyedArchiver {
+    fn default() -> KeyedArchiver {
+        KeyedArchiver::new()
+    }
+}
+
+/// Converts every dictionary with a single `CF$UID` integer into a UID.
+///
+/// XML and ASCII property lists are converted when read, so this is only
+/// needed for values built some other way. Integers written as strings, as
+/// ASCII property lists store them, are also accepted.
+pub fn resolve_uid_dicts(plist: Plist) -> Plist {
+    match plist {
+        Plist::Array(array) => Plist::Array(array.into_iter().map(resolve_uid_dicts).collect()),
+        Plist::Dict(dict) => {
+            if dict.len() == 1 {
+                if let Some(uid) = dict.get("CF$UID").and_then(uid_value) {
+                    return Plist::Uid(uid);
+                }
+            }
+            Plist::Dict(dict.into_iter().map(|(k, v)| (k, resolve_uid_dicts(v))).collect())
+        }
+        p => p,
+    }
+}
+
+#[inline]
+fn invalid(message: &str) -> Error {
+    ErrorKind::InvalidArchive(message.to_string()).into()
+}
+
+// ASCII property lists store integers as strings
+fn uid_value(plist: &Plist) -> Option<u64> {
+    match *plist {
+        Plist::Integer(i) => i.as_unsigned(),
+        Plist::String(ref s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn object_ref(plist: &Plist, len: usize) -> Result<ObjectRef> {
+    match *plist {
+        Plist::Uid(uid) if uid < len as u64 => Ok(ObjectRef(uid as usize)),
+        Plist::Uid(uid) => Err(ErrorKind::InvalidObjectReference(uid).into()),
+        _ => Err(invalid("a reference is not a UID")),
+    }
+}
+
+fn object_refs(plist: Option<&Plist>, len: usize) -> Result<Vec<ObjectRef>> {
+    match plist {
+        Some(&Plist::Array(ref array)) => array.iter().map(|r| object_ref(r, len)).collect(),
+        _ => Err(invalid("a collection does not have an array of references")),
+    }
+}
+
+fn class_plist(class: &Class) -> Plist {
+    let mut dict = Dictionary::default();
+    let classes = class.hierarchy.iter().cloned().map(Plist::String).collect();
+    dict.insert("$classes".to_string(), Plist::Array(classes));
+    dict.insert("$classname".to_string(), Plist::String(class.name.clone()));
+    Plist::Dict(dict)
+}
+
+fn decode_class(plist: &Plist) -> Result<Class> {
+    let dict = match *plist {
+        Plist::Dict(ref dict) => dict,
+        _ => return Err(invalid("a class is not a dictionary")),
+    };
+
+    let name = match dict.get("$classname") {
+        Some(&Plist::String(ref s)) => s.clone(),
+        _ => return Err(invalid("a class does not have a name")),
+    };
+    let hierarchy = match dict.get("$classes") {
+        Some(&Plist::Array(ref classes)) => {
+            classes.iter()
+                .filter_map(|c| match *c {
+                    Plist::String(ref s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect()
+        }
+        _ => vec![name.clone()],
+    };
+
+    Ok(Class {
+        name: name,
+        hierarchy: hierarchy,
+    })
+}
+
+fn decode_instance(dict: &Dictionary, objects: &[Plist]) -> Result<ArchivedObject> {
+    let len = objects.len();
+    let class_ref = try!(object_ref(try!(dict.get("$class")
+                                         .ok_or_else(|| invalid("an object has no class"))),
+                                    len));
+    let class = try!(decode_class(&objects[class_ref.0]));
+
+    // Subclasses of the Foundation classes are decoded as their superclass
+    let known = class.hierarchy
+        .iter()
+        .chain(Some(&class.name))
+        .map(|s| &s[..])
+        .find(|s| {
+            match *s {
+                "NSString" | "NSData" | "NSDate" | "NSArray" | "NSSet" | "NSDictionary" |
+                "NSNull" | "NSURL" | "NSUUID" => true,
+                _ => false,
+            }
+        });
+
+    Ok(match known {
+        Some("NSString") => {
+            match dict.get("NS.string") {
+                Some(&Plist::String(ref s)) => ArchivedObject::String(s.clone()),
+                _ => return Err(invalid("an NSString does not contain a string")),
+            }
+        }
+        Some("NSData") => {
+            match dict.get("NS.data") {
+                Some(&Plist::Data(ref d)) => ArchivedObject::Data(d.clone()),
+                _ => return Err(invalid("an NSData does not contain data")),
+            }
+        }
+        Some("NSDate") => {
+            match dict.get("NS.time") {
+                Some(&Plist::Real(r)) => ArchivedObject::Date(Date::from_absolute_time(r)),
+                Some(&Plist::Integer(i)) => {
+                    let secs = try!(i.as_signed().ok_or(ErrorKind::DateOutOfRange));
+                    ArchivedObject::Date(Date::from_absolute_time(secs as f64))
+                }
+                _ => return Err(invalid("an NSDate does not contain a time")),
+            }
+        }
+        Some("NSArray") => ArchivedObject::Array(try!(object_refs(dict.get("NS.objects"), len))),
+        Some("NSSet") => ArchivedObject::Set(try!(object_refs(dict.get("NS.objects"), len))),
+        Some("NSDictionary") => {
+            let keys = try!(object_refs(dict.get("NS.keys"), len));
+            let values = try!(object_refs(dict.get("NS.objects"), len));
+            if keys.len() != values.len() {
+                return Err(invalid("an NSDictionary has a different number of keys and values"));
+            }
+            ArchivedObject::Dictionary(keys.into_iter().zip(values).collect())
+        }
+        Some("NSNull") => ArchivedObject::Null,
+        Some("NSURL") => {
+            let base = match dict.get("NS.base") {
+                Some(r) => Some(try!(object_ref(r, len))).filter(|r| r.0 != 0),
+                None => None,
+            };
+            let relative = match dict.get("NS.relative").map(|r| object_ref(r, len)) {
+                Some(Ok(r)) => {
+                    match objects[r.0] {
+                        Plist::String(ref s) => s.clone(),
+                        _ => return Err(invalid("an NSURL does not contain a string")),
+                    }
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Err(invalid("an NSURL does not contain a string")),
+            };
+            ArchivedObject::Url {
+                base: base,
+                relative: relative,
+            }
+        }
+        Some("NSUUID") => {
+            match dict.get("NS.uuidbytes") {
+                Some(&Plist::Data(ref d)) if d.len() == 16 => {
+                    let mut bytes = [0; 16];
+                    bytes.copy_from_slice(d);
+                    ArchivedObject::Uuid(bytes)
+                }
+                _ => return Err(invalid("an NSUUID does not contain 16 bytes")),
+            }
+        }
+        _ => {
+            let mut fields = Map::default();
+            for (key, value) in dict.iter().filter(|&(k, _)| k != "$class") {
+                let field = match *value {
+                    Plist::Uid(_) => Field::Object(try!(object_ref(value, len))),
+                    ref v => Field::Value(v.clone()),
+                };
+                fields.insert(key.clone(), field);
+            }
+            ArchivedObject::Object {
+                class: class,
+                fields: fields,
+            }
+        }
+    })
+}
+
+fn decode_object(index: usize, plist: &Plist, objects: &[Plist]) -> Result<ArchivedObject> {
+    Ok(match *plist {
+        Plist::String(ref s) if index == 0 && s == "$null" => ArchivedObject::Nil,
+        Plist::String(ref s) => ArchivedObject::String(s.clone()),
+        Plist::Boolean(b) => ArchivedObject::Number(Number::Boolean(b)),
+        Plist::Integer(i) => ArchivedObject::Number(Number::Integer(i)),
+        Plist::Real(r) => ArchivedObject::Number(Number::Real(r)),
+        Plist::Data(ref d) => ArchivedObject::Data(d.clone()),
+        Plist::DateTime(d) => ArchivedObject::Date(d),
+        Plist::Dict(ref dict) if dict.contains_key("$class") => {
+            try!(decode_instance(dict, objects))
+        }
+        Plist::Dict(ref dict) if dict.contains_key("$classname") => {
+            ArchivedObject::Class(try!(decode_class(plist)))
+        }
+        _ => return Err(invalid("an object has an unsupported type")),
+    })
+}
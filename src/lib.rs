@@ -38,12 +38,34 @@
 
 extern crate chrono;
 extern crate fnv;
+extern crate indexmap;
 extern crate rustc_serialize;
 extern crate xml;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
+mod date;
+mod integer;
 mod result;
 mod plist;
+mod plist_ref;
 mod reader;
+mod writer;
 
-pub use result::{Result, Error};
-pub use plist::Plist;
+pub mod keyed_archive;
+
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
+
+pub use date::Date;
+pub use integer::Integer;
+pub use result::{Result, Error, ErrorKind, PathSegment, Position};
+pub use plist::{Plist, Dictionary};
+pub use plist_ref::{PlistRef, DictionaryRef};
+pub use reader::{Event, ReaderConfig};
+pub use reader::binary::{BinaryDocument, BinaryEventReader, Node, NodeKind};
+pub use reader::xml::XmlEventReader;
+pub use writer::WriterConfig;
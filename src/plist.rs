@@ -1,13 +1,23 @@
 use std::collections::HashMap;
-use std::hash::BuildHasherDefault;
-use std::io::{Read, Seek};
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, BuildHasherDefault};
+use std::io::{Cursor, Read, Seek, Write};
+use std::ops::Index;
 use std::time::SystemTime;
 use fnv::FnvHasher;
+use indexmap::IndexMap;
 
+use date::Date;
+use integer::Integer;
+use reader::ascii::from_ascii_reader;
 use reader::binary::from_binary_reader;
 use reader::xml::from_xml_reader;
-use reader::from_reader;
-use result::Result;
+use reader::{ReaderConfig, from_reader};
+use writer::ascii::to_ascii_writer;
+use writer::binary::to_binary_writer;
+use writer::xml::to_xml_writer;
+use writer::WriterConfig;
+use result::{Result, Error, ErrorKind};
 
 /// Represents a property list value.
 #[derive(Debug, Clone, PartialEq)]
@@ -21,32 +31,640 @@ pub enum Plist {
     /// A data value
     Data(Vec<u8>),
     /// A date value
-    DateTime(SystemTime),
+    DateTime(Date),
     /// A floating point value
     Real(f64),
     /// An integer value
-    Integer(i64),
+    Integer(Integer),
     /// A string value
     String(String),
+    /// A UID value, which refers to an object in an `NSKeyedArchiver` archive
+    ///
+    /// XML and ASCII property lists have no UID type, so a UID is written as
+    /// a dictionary with a single `CF$UID` integer. Reading XML converts such
+    /// dictionaries back to UIDs.
+    Uid(u64),
+    /// A null value
+    ///
+    /// Only binary property lists can store a null, so writing one to XML or
+    /// ASCII fails with `ErrorKind::NullNotSupported`.
+    Null,
 }
 
 pub type Array = Vec<Plist>;
-pub type Dictionary = HashMap<String, Plist, BuildHasherDefault<FnvHasher>>;
+
+/// A dictionary of plist objects, keyed by string.
+///
+/// Keys are kept in insertion order, which is the order they appear in the
+/// source document when read, and the order they are written in.
+pub type Dictionary = IndexMap<String, Plist, BuildHasherDefault<FnvHasher>>;
+
+/// Returned by indexing when a key or index is missing.
+static NULL: Plist = Plist::Null;
 
 impl Plist {
     /// Decodes a binary property list value from a reader.
+    ///
+    /// Sets and ordered sets are read as arrays, which is also how they are
+    /// written to XML, as it has no set type.
     pub fn from_binary_reader<R: Read + Seek>(input: &mut R) -> Result<Self> {
-        from_binary_reader(input)
+        from_binary_reader(input, &ReaderConfig::default())
+    }
+
+    /// Decodes a binary property list value from a reader, enforcing the
+    /// limits of `config`.
+    pub fn from_binary_reader_with_config<R: Read + Seek>(input: &mut R,
+                                                          config: &ReaderConfig)
+                                                          -> Result<Self> {
+        from_binary_reader(input, config)
     }
 
     /// Decodes an XML property list value from a reader.
     pub fn from_xml_reader<R: Read>(input: &mut R) -> Result<Self> {
-        from_xml_reader(input)
+        from_xml_reader(input, &ReaderConfig::default())
+    }
+
+    /// Decodes an XML property list value from a reader, enforcing the limits
+    /// of `config`.
+    pub fn from_xml_reader_with_config<R: Read>(input: &mut R,
+                                                config: &ReaderConfig)
+                                                -> Result<Self> {
+        from_xml_reader(input, config)
     }
 
-    /// Decodes a binary or XML property list value from a reader, based on
-    /// the presence of the binary plist magic bytes.
+    /// Decodes an ASCII (OpenStep) property list value from a reader.
+    ///
+    /// Strings files, which are dictionaries without the enclosing braces,
+    /// are also accepted. The input is read as UTF-8 unless it starts with a
+    /// UTF-16 byte order mark.
+    pub fn from_ascii_reader<R: Read>(input: &mut R) -> Result<Self> {
+        from_ascii_reader(input, &ReaderConfig::default())
+    }
+
+    /// Decodes an ASCII (OpenStep) property list value from a reader,
+    /// enforcing the limits of `config`.
+    pub fn from_ascii_reader_with_config<R: Read>(input: &mut R,
+                                                  config: &ReaderConfig)
+                                                  -> Result<Self> {
+        from_ascii_reader(input, config)
+    }
+
+    /// Decodes a binary, XML or ASCII property list value from a reader, based
+    /// on the presence of the binary plist magic bytes or an XML declaration.
     pub fn from_reader<R: Read + Seek>(input: &mut R) -> Result<Self> {
-        from_reader(input)
+        from_reader(input, &ReaderConfig::default())
+    }
+
+    /// Decodes a property list value of any format from a reader, enforcing
+    /// the limits of `config`.
+    ///
+    /// Untrusted input should be read with limits suited to the application,
+    /// as by default only the nesting depth is limited.
+    pub fn from_reader_with_config<R: Read + Seek>(input: &mut R,
+                                                   config: &ReaderConfig)
+                                                   -> Result<Self> {
+        from_reader(input, config)
+    }
+
+    /// Decodes a binary, XML or ASCII property list value from a byte slice.
+    ///
+    /// `PlistRef::from_slice` reads the slice without copying the strings and
+    /// data of binary property lists.
+    pub fn from_slice(input: &[u8]) -> Result<Self> {
+        from_reader(&mut Cursor::new(input), &ReaderConfig::default())
+    }
+
+    /// Decodes a property list value of any format from a byte slice,
+    /// enforcing the limits of `config`.
+    pub fn from_slice_with_config(input: &[u8], config: &ReaderConfig) -> Result<Self> {
+        from_reader(&mut Cursor::new(input), config)
+    }
+
+    /// Encodes the property list value as a binary property list to a writer.
+    pub fn to_binary_writer<W: Write>(&self, output: &mut W) -> Result<()> {
+        to_binary_writer(output, self, &WriterConfig::default())
+    }
+
+    /// Encodes the property list value as a binary property list to a writer,
+    /// with the options of `config`.
+    pub fn to_binary_writer_with_config<W: Write>(&self,
+                                                  output: &mut W,
+                                                  config: &WriterConfig)
+                                                  -> Result<()> {
+        to_binary_writer(output, self, config)
+    }
+
+    /// Encodes the property list value as an ASCII (OpenStep) property list to
+    /// a writer, formatted the way Xcode writes project files.
+    ///
+    /// The ASCII format only has strings, so booleans are written as `YES` or
+    /// `NO`, numbers as their decimal representation and dates in the
+    /// `2016-04-24 01:18:28 +0000` format. These values are read back as
+    /// strings.
+    ///
+    /// Dictionary keys are written in insertion order, whereas Xcode sorts
+    /// them. `WriterConfig::sort_keys` sorts them too.
+    pub fn to_ascii_writer<W: Write>(&self, output: &mut W) -> Result<()> {
+        to_ascii_writer(output, self, &WriterConfig::default())
+    }
+
+    /// Encodes the property list value as an ASCII (OpenStep) property list to
+    /// a writer, with the options of `config`.
+    pub fn to_ascii_writer_with_config<W: Write>(&self,
+                                                 output: &mut W,
+                                                 config: &WriterConfig)
+                                                 -> Result<()> {
+        to_ascii_writer(output, self, config)
+    }
+
+    /// Encodes the property list value as an XML property list to a writer,
+    /// formatted the way Apple's `plutil -convert xml1` formats it.
+    ///
+    /// Dictionary keys are written in insertion order, whereas `plutil` sorts
+    /// them, so the output is only identical to `plutil`'s when written with
+    /// `WriterConfig::sort_keys`.
+    pub fn to_xml_writer<W: Write>(&self, output: &mut W) -> Result<()> {
+        to_xml_writer(output, self, &WriterConfig::default())
+    }
+
+    /// Encodes the property list value as an XML property list to a writer,
+    /// with the options of `config`.
+    pub fn to_xml_writer_with_config<W: Write>(&self,
+                                               output: &mut W,
+                                               config: &WriterConfig)
+                                               -> Result<()> {
+        to_xml_writer(output, self, config)
+    }
+
+    /// Returns the string, if this is a `String`.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Plist::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer as an `i64`, if this is an `Integer` in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Plist::Integer(i) => i.as_signed(),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer as a `u64`, if this is an `Integer` in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Plist::Integer(i) => i.as_unsigned(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number, if this is a `Real`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Plist::Real(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is a `Boolean`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Plist::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes, if this is `Data`.
+    pub fn as_data(&self) -> Option<&[u8]> {
+        match *self {
+            Plist::Data(ref d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns the date, if this is a `DateTime`.
+    pub fn as_date(&self) -> Option<Date> {
+        match *self {
+            Plist::DateTime(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns the array, if this is an `Array`.
+    pub fn as_array(&self) -> Option<&Array> {
+        match *self {
+            Plist::Array(ref a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the dictionary, if this is a `Dict`.
+    pub fn as_dict(&self) -> Option<&Dictionary> {
+        match *self {
+            Plist::Dict(ref d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the string, if this is a `String`.
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match *self {
+            Plist::String(ref mut s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the integer, if this is an `Integer`.
+    pub fn as_integer_mut(&mut self) -> Option<&mut Integer> {
+        match *self {
+            Plist::Integer(ref mut i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the number, if this is a `Real`.
+    pub fn as_f64_mut(&mut self) -> Option<&mut f64> {
+        match *self {
+            Plist::Real(ref mut r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the boolean, if this is a `Boolean`.
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match *self {
+            Plist::Boolean(ref mut b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the bytes, if this is `Data`.
+    pub fn as_data_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match *self {
+            Plist::Data(ref mut d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the date, if this is a `DateTime`.
+    pub fn as_date_mut(&mut self) -> Option<&mut Date> {
+        match *self {
+            Plist::DateTime(ref mut d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the array, if this is an `Array`.
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match *self {
+            Plist::Array(ref mut a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the dictionary, if this is a `Dict`.
+    pub fn as_dict_mut(&mut self) -> Option<&mut Dictionary> {
+        match *self {
+            Plist::Dict(ref mut d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Converts into the string, if this is a `String`.
+    pub fn into_string(self) -> Option<String> {
+        match self {
+            Plist::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Converts into the bytes, if this is `Data`.
+    pub fn into_data(self) -> Option<Vec<u8>> {
+        match self {
+            Plist::Data(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Converts into the array, if this is an `Array`.
+    pub fn into_array(self) -> Option<Array> {
+        match self {
+            Plist::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Converts into the dictionary, if this is a `Dict`.
+    pub fn into_dict(self) -> Option<Dictionary> {
+        match self {
+            Plist::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Index<&'a str> for Plist {
+    type Output = Plist;
+
+    /// Looks up a key in a dictionary.
+    ///
+    /// Returns `Plist::Null` if this is not a dictionary or the key is
+    /// missing, so lookups can be chained as in `plist["a"]["b"]`.
+    fn index(&self, key: &str) -> &Plist {
+        match *self {
+            Plist::Dict(ref d) => d.get(key).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+impl Index<usize> for Plist {
+    type Output = Plist;
+
+    /// Looks up an element of an array.
+    ///
+    /// Returns `Plist::Null` if this is not an array or the index is out of
+    /// bounds.
+    fn index(&self, index: usize) -> &Plist {
+        match *self {
+            Plist::Array(ref a) => a.get(index).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+impl From<String> for Plist {
+    fn from(value: String) -> Plist {
+        Plist::String(value)
+    }
+}
+
+impl<'a> From<&'a str> for Plist {
+    fn from(value: &'a str) -> Plist {
+        Plist::String(value.to_string())
+    }
+}
+
+impl From<bool> for Plist {
+    fn from(value: bool) -> Plist {
+        Plist::Boolean(value)
+    }
+}
+
+impl From<Integer> for Plist {
+    fn from(value: Integer) -> Plist {
+        Plist::Integer(value)
+    }
+}
+
+// `u8` is left out so that `Vec<u8>` converts to data rather than an array
+macro_rules! impl_from_integer {
+    ($($t:ty)*) => {
+        $(
+            impl From<$t> for Plist {
+                fn from(value: $t) -> Plist {
+                    Plist::Integer(value.into())
+                }
+            }
+        )*
+    }
+}
+
+impl_from_integer!(i8 i16 i32 i64 u16 u32 u64);
+
+impl From<f32> for Plist {
+    fn from(value: f32) -> Plist {
+        Plist::Real(value.into())
+    }
+}
+
+impl From<f64> for Plist {
+    fn from(value: f64) -> Plist {
+        Plist::Real(value)
     }
 }
+
+impl From<Vec<u8>> for Plist {
+    fn from(value: Vec<u8>) -> Plist {
+        Plist::Data(value)
+    }
+}
+
+impl From<Date> for Plist {
+    fn from(value: Date) -> Plist {
+        Plist::DateTime(value)
+    }
+}
+
+impl From<SystemTime> for Plist {
+    fn from(value: SystemTime) -> Plist {
+        Plist::DateTime(value.into())
+    }
+}
+
+impl<T: Into<Plist>> From<Vec<T>> for Plist {
+    fn from(value: Vec<T>) -> Plist {
+        Plist::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Dictionary> for Plist {
+    fn from(value: Dictionary) -> Plist {
+        Plist::Dict(value)
+    }
+}
+
+impl<T: Into<Plist>, S: BuildHasher> From<HashMap<String, T, S>> for Plist {
+    fn from(value: HashMap<String, T, S>) -> Plist {
+        Plist::Dict(value.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl TryFrom<Plist> for String {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<String> {
+        value.into_string().ok_or(ErrorKind::UnexpectedType("String").into())
+    }
+}
+
+impl TryFrom<Plist> for bool {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<bool> {
+        value.as_bool().ok_or(ErrorKind::UnexpectedType("bool").into())
+    }
+}
+
+impl TryFrom<Plist> for Integer {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<Integer> {
+        match value {
+            Plist::Integer(i) => Ok(i),
+            _ => Err(ErrorKind::UnexpectedType("Integer").into()),
+        }
+    }
+}
+
+impl TryFrom<Plist> for i64 {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<i64> {
+        match value {
+            Plist::Integer(i) => i.as_signed().ok_or(ErrorKind::IntegerOutOfRange("i64").into()),
+            _ => Err(ErrorKind::UnexpectedType("i64").into()),
+        }
+    }
+}
+
+impl TryFrom<Plist> for u64 {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<u64> {
+        match value {
+            Plist::Integer(i) => i.as_unsigned().ok_or(ErrorKind::IntegerOutOfRange("u64").into()),
+            _ => Err(ErrorKind::UnexpectedType("u64").into()),
+        }
+    }
+}
+
+impl TryFrom<Plist> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<f64> {
+        value.as_f64().ok_or(ErrorKind::UnexpectedType("f64").into())
+    }
+}
+
+impl TryFrom<Plist> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<Vec<u8>> {
+        value.into_data().ok_or(ErrorKind::UnexpectedType("Vec<u8>").into())
+    }
+}
+
+impl TryFrom<Plist> for Date {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<Date> {
+        value.as_date().ok_or(ErrorKind::UnexpectedType("Date").into())
+    }
+}
+
+impl TryFrom<Plist> for SystemTime {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<SystemTime> {
+        let date = try!(value.as_date().ok_or(ErrorKind::UnexpectedType("SystemTime")));
+        SystemTime::try_from(date)
+    }
+}
+
+impl<T: TryFrom<Plist, Error = Error>> TryFrom<Plist> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<Vec<T>> {
+        let array = try!(value.into_array().ok_or(ErrorKind::UnexpectedType("Vec")));
+        array.into_iter().map(T::try_from).collect()
+    }
+}
+
+impl TryFrom<Plist> for Dictionary {
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<Dictionary> {
+        value.into_dict().ok_or(ErrorKind::UnexpectedType("Dictionary").into())
+    }
+}
+
+impl<T, S> TryFrom<Plist> for HashMap<String, T, S>
+    where T: TryFrom<Plist, Error = Error>,
+          S: BuildHasher + Default
+{
+    type Error = Error;
+
+    fn try_from(value: Plist) -> Result<HashMap<String, T, S>> {
+        let dict = try!(value.into_dict().ok_or(ErrorKind::UnexpectedType("HashMap")));
+        dict.into_iter().map(|(k, v)| T::try_from(v).map(|v| (k, v))).collect()
+    }
+}
+
+/// Constructs a `Plist` from a literal, in the style of `serde_json::json!`.
+///
+/// Arrays are written as `[...]` and dictionaries as `{ "key": value, ... }`.
+/// Any other value is converted with `Plist::from`, so it can be any
+/// expression of a type that converts into a `Plist`.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate plist;
+///
+/// fn main() {
+///     let plist = plist!({
+///         "Name": "Ada",
+///         "Age": 36,
+///         "Languages": ["English", "French"],
+///         "Data": vec![0u8, 1, 2],
+///     });
+///
+///     assert_eq!(plist["Languages"][1].as_str(), Some("French"));
+/// }
+/// ```
+#[macro_export]
+macro_rules! plist {
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] , $($rest:tt)*) => {
+        plist!(@array [$($elems,)*] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        plist!(@array [$($elems,)* plist!([$($array)*]),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($dict:tt)*} $($rest:tt)*) => {
+        plist!(@array [$($elems,)* plist!({$($dict)*}),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        plist!(@array [$($elems,)* plist!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        plist!(@array [$($elems,)* plist!($last),])
+    };
+
+    (@dict $dict:ident) => {};
+    (@dict $dict:ident , $($rest:tt)*) => {
+        plist!(@dict $dict $($rest)*);
+    };
+    (@dict $dict:ident $key:tt : [$($array:tt)*] $($rest:tt)*) => {
+        $dict.insert(($key).into(), plist!([$($array)*]));
+        plist!(@dict $dict $($rest)*);
+    };
+    (@dict $dict:ident $key:tt : {$($value:tt)*} $($rest:tt)*) => {
+        $dict.insert(($key).into(), plist!({$($value)*}));
+        plist!(@dict $dict $($rest)*);
+    };
+    (@dict $dict:ident $key:tt : $value:expr, $($rest:tt)*) => {
+        $dict.insert(($key).into(), plist!($value));
+        plist!(@dict $dict $($rest)*);
+    };
+    (@dict $dict:ident $key:tt : $value:expr) => {
+        $dict.insert(($key).into(), plist!($value));
+    };
+
+    ([$($tt:tt)*]) => {
+        $crate::Plist::Array(plist!(@array [] $($tt)*))
+    };
+    ({$($tt:tt)*}) => {{
+        #[allow(unused_mut)]
+        let mut dict = $crate::Dictionary::default();
+        plist!(@dict dict $($tt)*);
+        $crate::Plist::Dict(dict)
+    }};
+    ($other:expr) => {
+        $crate::Plist::from($other)
+    };
+}
@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+use std::hash::BuildHasherDefault;
+use std::io::Cursor;
+use fnv::FnvHasher;
+use indexmap::IndexMap;
+
+use date::Date;
+use integer::Integer;
+use plist::Plist;
+use reader::binary::from_binary_slice;
+use reader::{ReaderConfig, from_reader};
+use result::{Result, ErrorKind};
+
+/// Represents a property list value read from a byte slice, which borrows
+/// its strings and data from the slice where it can.
+///
+/// Only binary property lists store strings and data in a form that can be
+/// borrowed. UTF-16 strings, and every value of XML and ASCII property lists,
+/// are copied.
+///
+/// ```rust
+/// use std::borrow::Cow;
+/// use std::fs::File;
+/// use std::io::Read;
+/// use plist::PlistRef;
+///
+/// let mut buf = Vec::new();
+/// File::open("tests/types-binary.plist").unwrap().read_to_end(&mut buf).unwrap();
+///
+/// let plist = PlistRef::from_slice(&buf).unwrap();
+/// if let PlistRef::Dict(ref dict) = plist {
+///     assert_eq!(dict["String"], PlistRef::String(Cow::Borrowed("Hello")));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlistRef<'a> {
+    /// An array of plist objects
+    Array(Vec<PlistRef<'a>>),
+    /// A dictionary of plist objects, keyed by string
+    Dict(DictionaryRef<'a>),
+    /// A boolean value
+    Boolean(bool),
+    /// A data value
+    Data(Cow<'a, [u8]>),
+    /// A date value
+    DateTime(Date),
+    /// A floating point value
+    Real(f64),
+    /// An integer value
+    Integer(Integer),
+    /// A string value
+    String(Cow<'a, str>),
+    /// A UID value, which refers to an object in an `NSKeyedArchiver` archive
+    Uid(u64),
+    /// A null value
+    Null,
+}
+
+/// A dictionary of borrowed plist objects, keyed by string, in the order the
+/// keys appear in the source document.
+pub type DictionaryRef<'a> = IndexMap<Cow<'a, str>, PlistRef<'a>, BuildHasherDefault<FnvHasher>>;
+
+impl<'a> PlistRef<'a> {
+    /// Decodes a binary, XML or ASCII property list value from a byte slice,
+    /// borrowing the strings and data of binary property lists.
+    ///
+    /// Unlike `Plist::from_reader`, this is a good fit for memory-mapped
+    /// files, as only the offset table and UTF-16 strings of a binary
+    /// property list are copied from the input.
+    pub fn from_slice(input: &'a [u8]) -> Result<PlistRef<'a>> {
+        PlistRef::from_slice_with_config(input, &ReaderConfig::default())
+    }
+
+    /// Decodes a property list value of any format from a byte slice,
+    /// enforcing the limits of `config`.
+    pub fn from_slice_with_config(input: &'a [u8],
+                                  config: &ReaderConfig)
+                                  -> Result<PlistRef<'a>> {
+        match from_binary_slice(input, config) {
+            Err(ref e) if *e.kind() == ErrorKind::InvalidMagicBytes => (),
+            r => return r,
+        }
+        from_reader(&mut Cursor::new(input), config).map(PlistRef::from)
+    }
+
+    /// Copies any borrowed strings and data to convert the value to a
+    /// `Plist`.
+    pub fn into_owned(self) -> Plist {
+        match self {
+            PlistRef::Array(array) => {
+                Plist::Array(array.into_iter().map(PlistRef::into_owned).collect())
+            }
+            PlistRef::Dict(dict) => {
+                let dict = dict.into_iter().map(|(k, v)| (k.into_owned(), v.into_owned()));
+                Plist::Dict(dict.collect())
+            }
+            PlistRef::Boolean(b) => Plist::Boolean(b),
+            PlistRef::Data(d) => Plist::Data(d.into_owned()),
+            PlistRef::DateTime(d) => Plist::DateTime(d),
+            PlistRef::Real(r) => Plist::Real(r),
+            PlistRef::Integer(i) => Plist::Integer(i),
+            PlistRef::String(s) => Plist::String(s.into_owned()),
+            PlistRef::Uid(u) => Plist::Uid(u),
+            PlistRef::Null => Plist::Null,
+        }
+    }
+}
+
+impl<'a> From<Plist> for PlistRef<'a> {
+    fn from(plist: Plist) -> PlistRef<'a> {
+        match plist {
+            Plist::Array(array) => PlistRef::Array(array.into_iter().map(PlistRef::from).collect()),
+            Plist::Dict(dict) => {
+                PlistRef::Dict(dict.into_iter().map(|(k, v)| (Cow::Owned(k), v.into())).collect())
+            }
+            Plist::Boolean(b) => PlistRef::Boolean(b),
+            Plist::Data(d) => PlistRef::Data(Cow::Owned(d)),
+            Plist::DateTime(d) => PlistRef::DateTime(d),
+            Plist::Real(r) => PlistRef::Real(r),
+            Plist::Integer(i) => PlistRef::Integer(i),
+            Plist::String(s) => PlistRef::String(Cow::Owned(s)),
+            Plist::Uid(u) => PlistRef::Uid(u),
+            Plist::Null => PlistRef::Null,
+        }
+    }
+}
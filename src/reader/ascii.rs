@@ -0,0 +1,400 @@
+use std::hash::BuildHasherDefault;
+use std::io::Read;
+use std::iter::Peekable;
+use std::str::Chars;
+use fnv::FnvHasher;
+use indexmap::IndexMap;
+
+use plist::{Plist, Dictionary};
+use result::{Result, ErrorKind, PathSegment, Position, with_path, with_position};
+use super::ReaderConfig;
+
+// The configured limits along with the number of objects read so far, the
+// current nesting depth and the keys and indices of the value being read
+struct Limits {
+    config: ReaderConfig,
+    objects: u64,
+    depth: usize,
+    path: Vec<PathSegment>,
+}
+
+impl Limits {
+    fn object(&mut self) -> Result<()> {
+        self.objects += 1;
+        self.config.check_objects(self.objects)
+    }
+}
+
+#[inline]
+fn is_unquoted_char(c: char) -> bool {
+    match c {
+        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '$' | '/' | ':' | '.' | '-' => true,
+        _ => false,
+    }
+}
+
+#[inline]
+fn ascii_next(input: &mut Peekable<Chars>) -> Result<char> {
+    input.next().ok_or(ErrorKind::UnexpectedAsciiEof.into())
+}
+
+#[inline]
+fn ascii_expect(input: &mut Peekable<Chars>, expected: char) -> Result<()> {
+    match try!(ascii_next(input)) {
+        c if c == expected => Ok(()),
+        c => Err(ErrorKind::UnexpectedAsciiCharacter(c).into()),
+    }
+}
+
+fn ascii_whitespace(input: &mut Peekable<Chars>) -> Result<()> {
+    loop {
+        match input.peek() {
+            Some(&c) if c.is_whitespace() => {
+                input.next();
+            }
+            Some(&'/') => {
+                let mut lookahead = input.clone();
+                lookahead.next();
+                match lookahead.next() {
+                    Some('/') => {
+                        while let Some(c) = input.next() {
+                            if c == '\n' || c == '\r' {
+                                break;
+                            }
+                        }
+                    }
+                    Some('*') => {
+                        input.next();
+                        input.next();
+                        let mut previous = '\0';
+                        loop {
+                            let c = try!(ascii_next(input));
+                            if previous == '*' && c == '/' {
+                                break;
+                            }
+                            previous = c;
+                        }
+                    }
+                    _ => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+fn ascii_escape(input: &mut Peekable<Chars>, points: &mut Vec<u16>) -> Result<()> {
+    let c = try!(ascii_next(input));
+    let escaped = match c {
+        'a' => '\x07',
+        'b' => '\x08',
+        'f' => '\x0C',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'v' => '\x0B',
+        'U' => {
+            let mut point = 0u16;
+            for _ in 0..4 {
+                match input.peek().and_then(|c| c.to_digit(16)) {
+                    Some(d) => point = point << 4 | d as u16,
+                    None => break,
+                }
+                input.next();
+            }
+            points.push(point);
+            return Ok(());
+        }
+        '0'..='7' => {
+            let mut point = c.to_digit(8).unwrap_or(0) as u16;
+            for _ in 0..2 {
+                match input.peek().and_then(|c| c.to_digit(8)) {
+                    Some(d) => point = point << 3 | d as u16,
+                    None => break,
+                }
+                input.next();
+            }
+            points.push(point);
+            return Ok(());
+        }
+        c => c,
+    };
+
+    let mut buf = [0; 2];
+    points.extend_from_slice(escaped.encode_utf16(&mut buf));
+    Ok(())
+}
+
+fn ascii_quoted_string(input: &mut Peekable<Chars>, limits: &Limits) -> Result<String> {
+    let quote = try!(ascii_next(input));
+
+    // Escapes can produce unpaired UTF-16 surrogates, so the string is built
+    // up as UTF-16 and validated once complete
+    let mut points = Vec::new();
+    loop {
+        try!(limits.config.check_string(points.len() as u64));
+        match try!(ascii_next(input)) {
+            c if c == quote => break,
+            '\\' => try!(ascii_escape(input, &mut points)),
+            c => {
+                let mut buf = [0; 2];
+                points.extend_from_slice(c.encode_utf16(&mut buf));
+            }
+        }
+    }
+
+    let string = try!(String::from_utf16(&points[..]));
+    try!(limits.config.check_string(string.len() as u64));
+    Ok(string)
+}
+
+fn ascii_string(input: &mut Peekable<Chars>, limits: &Limits) -> Result<String> {
+    match input.peek() {
+        Some(&'"') | Some(&'\'') => return ascii_quoted_string(input, limits),
+        Some(&c) if is_unquoted_char(c) => (),
+        Some(&c) => return Err(ErrorKind::UnexpectedAsciiCharacter(c).into()),
+        None => return Err(ErrorKind::UnexpectedAsciiEof.into()),
+    }
+
+    let mut string = String::new();
+    while let Some(&c) = input.peek() {
+        if !is_unquoted_char(c) {
+            break;
+        }
+        string.push(c);
+        try!(limits.config.check_string(string.len() as u64));
+        input.next();
+    }
+    Ok(string)
+}
+
+fn ascii_data(input: &mut Peekable<Chars>, limits: &Limits) -> Result<Plist> {
+    try!(ascii_expect(input, '<'));
+
+    let mut data = Vec::new();
+    let mut high = None;
+    loop {
+        try!(ascii_whitespace(input));
+        match try!(ascii_next(input)) {
+            '>' if high.is_none() => break,
+            c => {
+                let digit = try!(c.to_digit(16)
+                    .ok_or(ErrorKind::UnexpectedAsciiCharacter(c))) as u8;
+                high = match high {
+                    Some(h) => {
+                        data.push(h << 4 | digit);
+                        try!(limits.config.check_data(data.len() as u64));
+                        None
+                    }
+                    None => Some(digit),
+                };
+            }
+        }
+    }
+
+    Ok(Plist::Data(data))
+}
+
+fn ascii_array(input: &mut Peekable<Chars>, limits: &mut Limits) -> Result<Plist> {
+    try!(ascii_expect(input, '('));
+    limits.depth += 1;
+    try!(limits.config.check_depth(limits.depth));
+
+    let mut array = Vec::new();
+    loop {
+        try!(ascii_whitespace(input));
+        if input.peek() == Some(&')') {
+            input.next();
+            break;
+        }
+
+        try!(limits.config.check_collection(array.len() as u64 + 1));
+        limits.path.push(PathSegment::Index(array.len() as u64));
+        array.push(try!(ascii_object(input, limits)));
+        limits.path.pop();
+
+        try!(ascii_whitespace(input));
+        match try!(ascii_next(input)) {
+            ',' => (),
+            ')' => break,
+            c => return Err(ErrorKind::UnexpectedAsciiCharacter(c).into()),
+        }
+    }
+
+    limits.depth -= 1;
+    Ok(Plist::Array(array))
+}
+
+fn ascii_dict_content(input: &mut Peekable<Chars>,
+                      dict: &mut Dictionary,
+                      limits: &mut Limits,
+                      terminator: Option<char>)
+                      -> Result<()> {
+    loop {
+        try!(ascii_whitespace(input));
+        match (input.peek().cloned(), terminator) {
+            (None, None) => return Ok(()),
+            (Some(c), Some(t)) if c == t => {
+                input.next();
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        try!(limits.config.check_collection(dict.len() as u64 + 1));
+        try!(limits.object());
+        let key = try!(ascii_string(input, limits));
+        try!(ascii_whitespace(input));
+
+        // Strings files allow a bare key, which maps the key to itself
+        let value = match try!(ascii_next(input)) {
+            '=' => {
+                limits.path.push(PathSegment::Key(key.clone()));
+                let value = try!(ascii_object(input, limits));
+                limits.path.pop();
+                try!(ascii_whitespace(input));
+                try!(ascii_expect(input, ';'));
+                value
+            }
+            ';' => {
+                try!(limits.object());
+                Plist::String(key.clone())
+            }
+            c => return Err(ErrorKind::UnexpectedAsciiCharacter(c).into()),
+        };
+        dict.insert(key, value);
+    }
+}
+
+fn ascii_dict(input: &mut Peekable<Chars>, limits: &mut Limits) -> Result<Plist> {
+    try!(ascii_expect(input, '{'));
+    limits.depth += 1;
+    try!(limits.config.check_depth(limits.depth));
+
+    let fnv = BuildHasherDefault::<FnvHasher>::default();
+    let mut dict = IndexMap::with_hasher(fnv);
+    try!(ascii_dict_content(input, &mut dict, limits, Some('}')));
+
+    limits.depth -= 1;
+    Ok(ascii_uid(dict))
+}
+
+// UIDs are written as a dictionary with a single CF$UID integer, which ASCII
+// property lists store as a string
+fn ascii_uid(dict: Dictionary) -> Plist {
+    if dict.len() == 1 {
+        if let Some(&Plist::String(ref s)) = dict.get("CF$UID") {
+            if let Ok(uid) = s.parse() {
+                return Plist::Uid(uid);
+            }
+        }
+    }
+    Plist::Dict(dict)
+}
+
+fn ascii_object(input: &mut Peekable<Chars>, limits: &mut Limits) -> Result<Plist> {
+    try!(ascii_whitespace(input));
+    try!(limits.object());
+    match input.peek() {
+        Some(&'{') => ascii_dict(input, limits),
+        Some(&'(') => ascii_array(input, limits),
+        Some(&'<') => ascii_data(input, limits),
+        Some(_) => Ok(Plist::String(try!(ascii_string(input, limits)))),
+        None => Err(ErrorKind::UnexpectedAsciiEof.into()),
+    }
+}
+
+fn ascii_document(input: &mut Peekable<Chars>, limits: &mut Limits) -> Result<Plist> {
+    // An empty document is an empty strings file
+    try!(ascii_whitespace(input));
+    let fnv = BuildHasherDefault::<FnvHasher>::default();
+    if input.peek().is_none() {
+        return Ok(Plist::Dict(IndexMap::with_hasher(fnv)));
+    }
+
+    // A strings file is a dictionary without the enclosing braces, which is
+    // only apparent from the token after the first string
+    let is_strings_file = match input.peek() {
+        Some(&'{') | Some(&'(') | Some(&'<') => false,
+        _ => {
+            let mut lookahead = input.clone();
+            ascii_string(&mut lookahead, limits).is_ok() &&
+            ascii_whitespace(&mut lookahead).is_ok() &&
+            match lookahead.peek() {
+                Some(&'=') | Some(&';') => true,
+                _ => false,
+            }
+        }
+    };
+    if is_strings_file {
+        let mut dict = IndexMap::with_hasher(fnv);
+        try!(ascii_dict_content(input, &mut dict, limits, None));
+        return Ok(Plist::Dict(dict));
+    }
+
+    let object = try!(ascii_object(input, limits));
+    try!(ascii_whitespace(input));
+    match input.next() {
+        Some(c) => Err(ErrorKind::UnexpectedAsciiCharacter(c).into()),
+        None => Ok(object),
+    }
+}
+
+// The line and column of the last character read, which is the one an error
+// occurred at
+fn ascii_position(text: &str, read: usize) -> Position {
+    let mut line = 1;
+    let mut column = 1;
+    for c in text.chars().take(read.saturating_sub(1)) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    Position::Text {
+        line: line,
+        column: column,
+    }
+}
+
+// Strings files are often UTF-16, in which case they start with a byte order
+// mark. Anything else is read as UTF-8.
+fn ascii_text(bytes: Vec<u8>) -> Result<String> {
+    let big_endian = match bytes.get(..2) {
+        Some(&[0xFE, 0xFF]) => true,
+        Some(&[0xFF, 0xFE]) => false,
+        _ => return Ok(try!(String::from_utf8(bytes))),
+    };
+    if bytes.len() % 2 != 0 {
+        return Err(ErrorKind::InvalidUtf16.into());
+    }
+
+    let points: Vec<u16> = bytes.chunks(2)
+        .map(|x| {
+            let (high, low) = if big_endian { (x[0], x[1]) } else { (x[1], x[0]) };
+            (high as u16) << 8 | low as u16
+        })
+        .collect();
+    Ok(try!(String::from_utf16(&points[..])))
+}
+
+pub fn from_ascii_reader<R: Read>(input: &mut R, config: &ReaderConfig) -> Result<Plist> {
+    let mut bytes = Vec::new();
+    try!(input.read_to_end(&mut bytes));
+    let string = try!(ascii_text(bytes));
+    let text = string.trim_start_matches('\u{FEFF}');
+    let mut chars = text.chars().peekable();
+    let mut limits = Limits {
+        config: *config,
+        objects: 0,
+        depth: 0,
+        path: Vec::new(),
+    };
+
+    ascii_document(&mut chars, &mut limits).map_err(|e| {
+        let read = text.chars().count() - chars.count();
+        with_position(with_path(e, limits.path), ascii_position(text, read))
+    })
+}
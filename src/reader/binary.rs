@@ -1,13 +1,22 @@
-use std::collections::HashMap;
-use std::hash::BuildHasherDefault;
-use std::io::{Read, Seek, SeekFrom};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::iter;
 use std::mem;
 use std::str;
-use std::time::{Duration, UNIX_EPOCH};
-use fnv::FnvHasher;
+use std::vec;
 
+use date::Date;
+use integer::Integer;
 use plist::Plist;
-use result::{Result, Error};
+use plist_ref::{PlistRef, DictionaryRef};
+use result::{Result, Error, ErrorKind, PathSegment, Position, with_path, with_position};
+use super::{Event, ReaderConfig, build};
+
+// Lengths are read from the file, so buffers grow as bytes are actually read
+// instead of being allocated up front
+const MAX_PREALLOCATION: usize = 64 * 1024;
 
 #[inline]
 fn be_u16(buf: &[u8]) -> u16 {
@@ -40,10 +49,10 @@ fn be_f64(buf: &[u8]) -> f64 {
 
 #[inline]
 fn validate_size(size: u8) -> Result<u8> {
-    if (size & (!size + 1)) == size && size >> 4 == 0 {
+    if size.is_power_of_two() && size <= 8 {
         Ok(size)
     } else {
-        return Err(Error::InvalidIntegerSize);
+        return Err(ErrorKind::InvalidIntegerSize.into());
     }
 }
 
@@ -59,10 +68,20 @@ fn sized_int(buf: &[u8], size: u8) -> u64 {
 }
 
 #[inline]
-fn sized_ints<R: Read>(input: &mut R, size: u8, count: usize) -> Result<Vec<u64>> {
-    let len = size as usize * count;
-    let mut buf = Vec::with_capacity(len);
+fn read_bytes<R: Read>(input: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(cmp::min(len, MAX_PREALLOCATION));
     try!(input.take(len as u64).read_to_end(&mut buf));
+    if buf.len() == len {
+        Ok(buf)
+    } else {
+        Err(ErrorKind::UnexpectedEof.into())
+    }
+}
+
+#[inline]
+fn sized_ints<R: Read>(input: &mut R, size: u8, count: usize) -> Result<Vec<u64>> {
+    let len = try!((size as usize).checked_mul(count).ok_or(ErrorKind::UnexpectedEof));
+    let buf = try!(read_bytes(input, len));
     Ok(buf.chunks(size as usize)
         .map(|x| sized_int(x, size))
         .collect())
@@ -74,7 +93,7 @@ fn read_sized<R: Read>(input: &mut R) -> Result<([u8; 8], u8)> {
     let mut buf = [0; 8];
     try!(input.read_exact(&mut len));
 
-    let size = try!(validate_size(1u8 << (len[0] & 0xF)));
+    let size = try!(validate_size(1u8.checked_shl((len[0] & 0xF) as u32).unwrap_or(0)));
     try!(input.read_exact(&mut buf[0..size as usize]));
     Ok((buf, size))
 }
@@ -92,174 +111,835 @@ fn read_int<R: Read>(input: &mut R) -> Result<u64> {
 }
 
 #[inline]
-fn trailer<R: Read + Seek>(input: &mut R) -> Result<(usize, u8, Vec<u64>)> {
+fn trailer<R: Read + Seek>(input: &mut R, config: &ReaderConfig) -> Result<(u64, u8, Vec<u64>)> {
+    // The file must at least hold the header and the trailer
+    let len = try!(input.seek(SeekFrom::End(0)));
+    if len < 8 + 32 {
+        return Err(ErrorKind::InvalidTrailer.into());
+    }
+
     let mut trailer = [0; 26];
     try!(input.seek(SeekFrom::End(-26)));
     try!(input.read_exact(&mut trailer));
 
-    let offset_size = try!(validate_size(trailer[0]));
-    let ref_size = try!(validate_size(trailer[1]));
+    let offset_size = try!(validate_size(trailer[0]).map_err(|_| ErrorKind::InvalidTrailer));
+    let ref_size = try!(validate_size(trailer[1]).map_err(|_| ErrorKind::InvalidTrailer));
     let obj_count = be_u64(&trailer[2..]);
-    let root = be_u64(&trailer[10..]) as usize;
+    let root = be_u64(&trailer[10..]);
     let table_offset = be_u64(&trailer[18..]);
 
+    // The offset table must lie between the header and the trailer
+    let table_end = obj_count.checked_mul(offset_size as u64)
+        .and_then(|table_len| table_offset.checked_add(table_len));
+    match table_end {
+        Some(end) if table_offset >= 8 && end <= len - 32 => (),
+        _ => return Err(ErrorKind::InvalidTrailer.into()),
+    }
+    if root >= obj_count {
+        return Err(ErrorKind::InvalidObjectReference(root).into());
+    }
+    try!(config.check_objects(obj_count));
+
     try!(input.seek(SeekFrom::Start(table_offset)));
     let offsets = try!(sized_ints(input, offset_size, obj_count as usize));
 
+    // Objects must lie between the header and the offset table
+    if let Some(&offset) = offsets.iter().find(|&&o| o < 8 || o >= table_offset) {
+        return Err(ErrorKind::InvalidObjectOffset(offset).into());
+    }
+
     Ok((root, ref_size, offsets))
 }
 
 #[inline]
-fn boolean<R: Read>(input: &mut R) -> Result<Plist> {
+fn singleton<R: Read>(input: &mut R) -> Result<Event> {
     let mut buf = [0; 1];
     try!(input.read_exact(&mut buf));
     match buf[0] & 0xF {
-        0x8 => Ok(Plist::Boolean(false)),
-        0x9 => Ok(Plist::Boolean(true)),
-        _ => Err(Error::InvalidBoolean),
+        // Fill bytes are only used for padding, so are read as a null
+        0x0 | 0xF => Ok(Event::Null),
+        0x8 => Ok(Event::Boolean(false)),
+        0x9 => Ok(Event::Boolean(true)),
+        _ => Err(ErrorKind::InvalidBoolean.into()),
     }
 }
 
 #[inline]
-fn integer<R: Read + Seek>(input: &mut R) -> Result<Plist> {
-    try!(input.seek(SeekFrom::Current(1)));
-    Ok(Plist::Integer(try!(read_int(input)) as i64))
+fn integer<R: Read>(input: &mut R) -> Result<Event> {
+    let mut marker = [0; 1];
+    try!(input.read_exact(&mut marker));
+
+    // Integers of up to four bytes are unsigned, eight byte integers are
+    // signed, and sixteen byte integers hold values above i64::MAX
+    let integer = match marker[0] & 0xF {
+        size @ 0..=2 => {
+            let size = 1 << size;
+            Integer::from(sized_int(&try!(read_bytes(input, size as usize)), size))
+        }
+        3 => Integer::from(be_u64(&try!(read_bytes(input, 8))) as i64),
+        4 => {
+            let buf = try!(read_bytes(input, 16));
+            match (be_u64(&buf[..8]), be_u64(&buf[8..])) {
+                (0, low) => Integer::from(low),
+                (high, low) if high == u64::max_value() && (low as i64) < 0 => {
+                    Integer::from(low as i64)
+                }
+                _ => return Err(ErrorKind::InvalidIntegerSize.into()),
+            }
+        }
+        _ => return Err(ErrorKind::InvalidIntegerSize.into()),
+    };
+    Ok(Event::Integer(integer))
+}
+
+#[inline]
+fn uid<R: Read>(input: &mut R) -> Result<Event> {
+    let mut marker = [0; 1];
+    try!(input.read_exact(&mut marker));
+
+    // UIDs are stored in one more byte than the marker specifies
+    let size = (marker[0] & 0xF) as usize + 1;
+    if size > 8 {
+        return Err(ErrorKind::InvalidIntegerSize.into());
+    }
+    let buf = try!(read_bytes(input, size));
+    Ok(Event::Uid(buf.iter().fold(0, |uid, &b| uid << 8 | b as u64)))
 }
 
 #[inline]
-fn real<R: Read>(input: &mut R) -> Result<Plist> {
+fn real<R: Read>(input: &mut R) -> Result<Event> {
     let (buf, len) = try!(read_sized(input));
     let real = match len {
         4 => be_f32(&buf) as f64,
         8 => be_f64(&buf),
-        _ => return Err(Error::InvalidIntegerSize),
+        _ => return Err(ErrorKind::InvalidIntegerSize.into()),
     };
-    Ok(Plist::Real(real))
+    Ok(Event::Real(real))
 }
 
 #[inline]
-fn date<R: Read>(input: &mut R) -> Result<Plist> {
+fn date<R: Read>(input: &mut R) -> Result<Event> {
     let mut buf = [0; 9];
     try!(input.read_exact(&mut buf));
-    let secs = be_f64(&buf[1..]);
-    let ref_date = UNIX_EPOCH + Duration::from_secs(978307200);
-    let duration = Duration::new(secs.trunc() as u64, (secs.fract() * 10e9) as u32);
-    Ok(Plist::DateTime(ref_date + duration))
+    Ok(Event::DateTime(Date::from_absolute_time(be_f64(&buf[1..]))))
 }
 
 #[inline]
-fn data<R: Read>(input: &mut R) -> Result<Plist> {
-    let len = try!(read_int(input)) as usize;
-    let mut buf = Vec::with_capacity(len);
-    try!(input.take(len as u64).read_to_end(&mut buf));
-    Ok(Plist::Data(buf))
+fn data<R: Read>(input: &mut R, config: &ReaderConfig) -> Result<Event> {
+    let len = try!(read_int(input));
+    try!(config.check_data(len));
+    Ok(Event::Data(try!(read_bytes(input, len as usize))))
 }
 
 #[inline]
-fn string<R: Read>(input: &mut R) -> Result<Plist> {
-    let len = try!(read_int(input)) as usize;
-    let mut buf = Vec::with_capacity(len);
-    try!(input.take(len as u64).read_to_end(&mut buf));
-    Ok(Plist::String(try!(String::from_utf8(buf))))
+fn string<R: Read>(input: &mut R, config: &ReaderConfig) -> Result<String> {
+    let len = try!(read_int(input));
+    try!(config.check_string(len));
+    let buf = try!(read_bytes(input, len as usize));
+    Ok(try!(String::from_utf8(buf)))
 }
 
 #[inline]
-fn utf16_string<R: Read>(input: &mut R) -> Result<Plist> {
-    let len = try!(read_int(input)) as usize;
-    let mut buf = Vec::with_capacity(len * 2);
-    try!(input.take((len * 2) as u64).read_to_end(&mut buf));
+fn utf16_string<R: Read>(input: &mut R, config: &ReaderConfig) -> Result<String> {
+    let len = try!(read_int(input));
+    try!(config.check_string(len));
+    let len = try!((len as usize).checked_mul(2).ok_or(ErrorKind::UnexpectedEof));
+    let buf = try!(read_bytes(input, len));
     let points: Vec<u16> = buf.chunks(2).map(|x| be_u16(x)).collect();
-    Ok(Plist::String(try!(String::from_utf16(&points[..]))))
+    Ok(try!(String::from_utf16(&points[..])))
 }
 
 #[inline]
-fn array<R: Read + Seek>(input: &mut R, ref_size: u8, offsets: &Vec<u64>) -> Result<Plist> {
-    let len = try!(read_int(input)) as usize;
-    let values = try!(sized_ints(input, ref_size, len));
+fn magic<R: Read + Seek>(input: &mut R) -> Result<()> {
+    try!(input.seek(SeekFrom::Start(0)));
 
-    let mut array = Vec::with_capacity(len);
-    for v in values {
-        let value = try!(object(input, v as usize, ref_size, offsets));
-        array.push(value);
+    let mut magic = [0; 6];
+    match input.read_exact(&mut magic) {
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            return Err(ErrorKind::InvalidMagicBytes.into())
+        }
+        r => try!(r),
+    }
+    if let Ok(s) = str::from_utf8(&magic) {
+        if s != "bplist" {
+            return Err(ErrorKind::InvalidMagicBytes.into());
+        }
+    } else {
+        return Err(ErrorKind::InvalidMagicBytes.into());
     }
 
-    Ok(Plist::Array(array))
+    let mut ver = [0; 2];
+    try!(input.read_exact(&mut ver));
+    if let Ok(s) = str::from_utf8(&ver) {
+        if s != "00" {
+            return Err(ErrorKind::VersionNotSupported(Some(s.to_string())).into());
+        }
+    } else {
+        return Err(ErrorKind::VersionNotSupported(None).into());
+    }
+    Ok(())
 }
 
-#[inline]
-fn dict<R: Read + Seek>(input: &mut R, ref_size: u8, offsets: &Vec<u64>) -> Result<Plist> {
-    let len = try!(read_int(input)) as usize;
-    let keys = try!(sized_ints(input, ref_size, len));
-    let values = try!(sized_ints(input, ref_size, len));
-
-    let fnv = BuildHasherDefault::<FnvHasher>::default();
-    let mut dict = HashMap::with_capacity_and_hasher(len, fnv);
-
-    for (k, v) in keys.into_iter().zip(values.into_iter()) {
-        let key = match try!(object(input, k as usize, ref_size, offsets)) {
-            Plist::String(s) => s,
-            _ => return Err(Error::InvalidKeyObject),
+// Reads the root object, reference size and offset table of a binary
+// property list
+fn header<R: Read + Seek>(input: &mut R, config: &ReaderConfig) -> Result<(u64, u8, Vec<u64>)> {
+    try!(magic(input).map_err(|e| with_position(e, Position::Binary { object: None, offset: 0 })));
+
+    let offset = try!(input.seek(SeekFrom::End(0))).saturating_sub(32);
+    trailer(input, config).map_err(|e| {
+        let e = match *e.kind() {
+            ErrorKind::Io |
+            ErrorKind::UnexpectedEof => ErrorKind::InvalidTrailer.into(),
+            _ => e,
         };
-
-        let value = try!(object(input, v as usize, ref_size, offsets));
-        dict.insert(key, value);
-    }
-
-    Ok(Plist::Dict(dict))
+        with_position(e, Position::Binary { object: None, offset: offset })
+    })
 }
 
-fn object<R: Read + Seek>(input: &mut R,
-                          obj: usize,
-                          ref_size: u8,
-                          offsets: &Vec<u64>)
-                          -> Result<Plist> {
+// Seeks to an object and returns its marker byte, leaving the input at the
+// start of the object
+fn seek_object<R: Read + Seek>(input: &mut R, offsets: &[u64], obj: u64) -> Result<u8> {
+    let offset = match offsets.get(obj as usize) {
+        Some(&offset) if obj < offsets.len() as u64 => SeekFrom::Start(offset),
+        _ => return Err(ErrorKind::InvalidObjectReference(obj).into()),
+    };
+
     let mut buf = [0; 1];
-    let offset = SeekFrom::Start(offsets[obj]);
     try!(input.seek(offset));
     try!(input.read_exact(&mut buf));
     try!(input.seek(offset));
+    Ok(buf[0])
+}
+
+// Reads the references of an array, or the key references followed by the
+// value references of a dictionary
+fn collection_refs<R: Read>(input: &mut R,
+                            ref_size: u8,
+                            config: &ReaderConfig,
+                            is_dict: bool)
+                            -> Result<Vec<u64>> {
+    let len = try!(read_int(input));
+    try!(config.check_collection(len));
+    let count = if is_dict { len.saturating_mul(2) } else { len };
+    sized_ints(input, ref_size, count as usize)
+}
 
-    let obj_type = buf[0] >> 4;
+#[inline]
+fn key<R: Read>(input: &mut R, obj_type: u8, config: &ReaderConfig) -> Result<String> {
     match obj_type {
-        0x0 => boolean(input),
+        0x5 => string(input, config),
+        0x6 => utf16_string(input, config),
+        _ => Err(ErrorKind::InvalidKeyObject.into()),
+    }
+}
+
+// Reads any object other than a collection
+fn scalar<R: Read>(input: &mut R, obj_type: u8, config: &ReaderConfig) -> Result<Event> {
+    match obj_type {
+        0x0 => singleton(input),
         0x1 => integer(input),
         0x2 => real(input),
         0x3 => date(input),
-        0x4 => data(input),
-        0x5 => string(input),
-        0x6 => utf16_string(input),
-        0xA => array(input, ref_size, offsets),
-        0xD => dict(input, ref_size, offsets),
-        _ => Err(Error::ObjectNotSupported(obj_type)),
+        0x4 => data(input, config),
+        0x5 | 0x6 => Ok(Event::String(try!(key(input, obj_type, config)))),
+        0x8 => uid(input),
+        _ => Err(ErrorKind::ObjectNotSupported(obj_type).into()),
     }
 }
 
-pub fn from_binary_reader<R: Read + Seek>(input: &mut R) -> Result<Plist> {
-    try!(input.seek(SeekFrom::Start(0)));
+// Decodes a dictionary key, or fails for any object other than a string
+fn read_key<R: Read + Seek>(input: &mut R,
+                            offsets: &[u64],
+                            obj: u64,
+                            config: &ReaderConfig)
+                            -> Result<String> {
+    let obj_type = try!(seek_object(input, offsets, obj)) >> 4;
+    key(input, obj_type, config)
+}
 
-    let mut magic = [0; 6];
-    try!(input.read_exact(&mut magic));
-    if let Ok(s) = str::from_utf8(&magic) {
-        if s != "bplist" {
-            return Err(Error::InvalidMagicBytes);
+#[derive(Debug)]
+struct Collection {
+    obj: u64,
+    refs: vec::IntoIter<u64>,
+    is_dict: bool,
+    key_next: bool,
+    // The number of values read so far, and the object of the current key,
+    // which is only decoded again for errors
+    len: u64,
+    key: Option<u64>,
+}
+
+// How far the reader has got through a value, with the input left at the
+// start of any key or value object for the caller to decode
+enum Step {
+    StartArray(u64),
+    StartDict(u64),
+    Key(u8),
+    Value(u8),
+    EndCollection,
+}
+
+/// An iterator over the events of a binary property list, which decodes
+/// objects as they are reached instead of building the whole `Plist` in
+/// memory.
+#[derive(Debug)]
+pub struct BinaryEventReader<R> {
+    input: R,
+    ref_size: u8,
+    offsets: Vec<u64>,
+    stack: Vec<Collection>,
+    config: ReaderConfig,
+    root: Option<u64>,
+    // Objects referenced more than once are counted each time they are read
+    objects: u64,
+    started: bool,
+    finished: bool,
+    // The object being read and its offset, which errors are reported at
+    object: Option<u64>,
+    offset: u64,
+}
+
+impl<R: Read + Seek> BinaryEventReader<R> {
+    /// Creates an event reader over a binary property list.
+    pub fn new(input: R) -> BinaryEventReader<R> {
+        BinaryEventReader::with_config(input, ReaderConfig::default())
+    }
+
+    /// Creates an event reader over a binary property list, which fails with
+    /// `ErrorKind::MaxDepthExceeded` when arrays and dictionaries are nested more
+    /// than `max_depth` levels deep.
+    pub fn with_max_depth(input: R, max_depth: usize) -> BinaryEventReader<R> {
+        let config = ReaderConfig { max_depth: max_depth, ..ReaderConfig::default() };
+        BinaryEventReader::with_config(input, config)
+    }
+
+    /// Creates an event reader over a binary property list, which enforces
+    /// the limits of `config`.
+    pub fn with_config(input: R, config: ReaderConfig) -> BinaryEventReader<R> {
+        BinaryEventReader {
+            input: input,
+            ref_size: 0,
+            offsets: Vec::new(),
+            stack: Vec::new(),
+            config: config,
+            root: None,
+            objects: 0,
+            started: false,
+            finished: false,
+            object: None,
+            offset: 0,
         }
-    } else {
-        return Err(Error::InvalidMagicBytes);
     }
 
-    let mut ver = [0; 2];
-    try!(input.read_exact(&mut ver));
-    if let Ok(s) = str::from_utf8(&ver) {
-        if s != "00" {
-            return Err(Error::VersionNotSupported(Some(s.to_string())));
+    // Reads the trailer and offset table, and returns the root object
+    fn header(&mut self) -> Result<u64> {
+        self.started = true;
+        let (root, ref_size, offsets) = try!(header(&mut self.input, &self.config));
+        self.ref_size = ref_size;
+        self.offsets = offsets;
+        Ok(root)
+    }
+
+    // Starts reading the events of `obj` once the header has been read,
+    // abandoning whatever was being read before
+    fn start_at(&mut self, obj: u64) {
+        self.stack.clear();
+        self.root = Some(obj);
+        self.objects = 0;
+        self.finished = false;
+    }
+
+    fn seek_object(&mut self, obj: u64) -> Result<u8> {
+        if let Some(&offset) = self.offsets.get(obj as usize) {
+            self.object = Some(obj);
+            self.offset = offset;
         }
-    } else {
-        return Err(Error::VersionNotSupported(None));
+        Ok(try!(seek_object(&mut self.input, &self.offsets, obj)) >> 4)
     }
 
-    if let Ok((root, ref_size, offsets)) = trailer(input) {
-        object(input, root, ref_size, &offsets)
-    } else {
-        Err(Error::InvalidTrailer)
+    fn collection(&mut self, obj: u64, is_dict: bool) -> Result<u64> {
+        // A collection that contains itself would never end
+        if self.stack.iter().any(|c| c.obj == obj) {
+            return Err(ErrorKind::RecursiveObject(obj).into());
+        }
+        try!(self.config.check_depth(self.stack.len() + 1));
+
+        let mut refs = try!(collection_refs(&mut self.input, self.ref_size, &self.config, is_dict));
+        let len = if is_dict { refs.len() / 2 } else { refs.len() };
+        if is_dict {
+            // Interleave the keys with their values
+            let values = refs.split_off(len);
+            let keys = refs;
+            refs = Vec::with_capacity(len * 2);
+            for (k, v) in keys.into_iter().zip(values.into_iter()) {
+                refs.push(k);
+                refs.push(v);
+            }
+        }
+
+        self.stack.push(Collection {
+            obj: obj,
+            refs: refs.into_iter(),
+            is_dict: is_dict,
+            key_next: true,
+            len: 0,
+            key: None,
+        });
+        Ok(len as u64)
+    }
+
+    fn count_object(&mut self) -> Result<()> {
+        self.objects += 1;
+        self.config.check_objects(self.objects)
+    }
+
+    fn object(&mut self, obj: u64) -> Result<Step> {
+        let obj_type = try!(self.seek_object(obj));
+        try!(self.count_object());
+        match obj_type {
+            0xA => Ok(Step::StartArray(try!(self.collection(obj, false)))),
+            // Ordered sets and sets have the same layout as arrays
+            0xB | 0xC if self.config.sets_as_arrays => {
+                Ok(Step::StartArray(try!(self.collection(obj, false))))
+            }
+            0xD => Ok(Step::StartDict(try!(self.collection(obj, true)))),
+            _ => Ok(Step::Value(obj_type)),
+        }
     }
+
+    // Moves to the next key or value, or past the end of a collection. Every
+    // way of reading a binary property list goes through here, so that
+    // cycles, nesting and the number of objects are checked in one place
+    fn step(&mut self) -> Result<Option<Step>> {
+        if !self.started {
+            let root = try!(self.header());
+            self.root = Some(root);
+        }
+
+        if let Some(root) = self.root.take() {
+            return self.object(root).map(Some);
+        }
+
+        let next = match self.stack.last_mut() {
+            Some(collection) => {
+                // Invalid references are reported at the collection
+                self.object = Some(collection.obj);
+                self.offset = self.offsets[collection.obj as usize];
+                collection.refs.next().map(|obj| {
+                    let is_key = collection.is_dict && collection.key_next;
+                    collection.key_next = !is_key;
+                    if is_key {
+                        collection.key = Some(obj);
+                    } else {
+                        collection.len += 1;
+                    }
+                    (obj, is_key)
+                })
+            }
+            None => return Ok(None),
+        };
+
+        match next {
+            Some((obj, true)) => {
+                let obj_type = try!(self.seek_object(obj));
+                try!(self.count_object());
+                Ok(Some(Step::Key(obj_type)))
+            }
+            Some((obj, false)) => self.object(obj).map(Some),
+            None => {
+                self.stack.pop();
+                Ok(Some(Step::EndCollection))
+            }
+        }
+    }
+
+    fn read_next(&mut self) -> Result<Option<Event>> {
+        let event = match try!(self.step()) {
+            Some(Step::StartArray(len)) => Event::StartArray(Some(len)),
+            Some(Step::StartDict(len)) => Event::StartDict(Some(len)),
+            Some(Step::Key(obj_type)) => {
+                Event::Key(try!(key(&mut self.input, obj_type, &self.config)))
+            }
+            Some(Step::Value(obj_type)) => try!(scalar(&mut self.input, obj_type, &self.config)),
+            Some(Step::EndCollection) => Event::EndCollection,
+            None => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+
+    // The keys and indices of the values currently being read. Keys that
+    // cannot be decoded are left out
+    fn path(&mut self) -> Vec<PathSegment> {
+        let input = &mut self.input;
+        let offsets = &self.offsets;
+        let config = &self.config;
+        self.stack
+            .iter()
+            .filter_map(|c| if c.is_dict {
+                c.key
+                    .and_then(|k| read_key(input, offsets, k, config).ok())
+                    .map(PathSegment::Key)
+            } else if c.len > 0 {
+                Some(PathSegment::Index(c.len - 1))
+            } else {
+                None
+            })
+            .collect()
+    }
+
+    // Errors are reported at the path and position of the object being read
+    fn error(&mut self, e: Error) -> Error {
+        let position = Position::Binary {
+            object: self.object,
+            offset: self.offset,
+        };
+        let path = self.path();
+        with_position(with_path(e, path), position)
+    }
+
+    // Errors are reported at an object, and references to objects that do
+    // not exist at the collection holding them
+    fn at(&self, e: Error, obj: u64) -> Error {
+        match self.offsets.get(obj as usize) {
+            Some(&offset) if obj < self.offsets.len() as u64 => {
+                with_position(e,
+                              Position::Binary {
+                                  object: Some(obj),
+                                  offset: offset,
+                              })
+            }
+            _ => e,
+        }
+    }
+
+    // Reads whether an object is a collection, whether it is a dictionary
+    // and its length, leaving the input at its references
+    fn collection_len(&mut self, obj: u64) -> Result<Option<(bool, u64)>> {
+        let is_dict = match try!(seek_object(&mut self.input, &self.offsets, obj)) >> 4 {
+            0xA => false,
+            0xB | 0xC if self.config.sets_as_arrays => false,
+            0xD => true,
+            _ => return Ok(None),
+        };
+        let len = try!(read_int(&mut self.input));
+        try!(self.config.check_collection(len));
+        Ok(Some((is_dict, len)))
+    }
+
+    // Reads the reference at `index` of the collection the input is at
+    fn reference(&mut self, index: u64) -> Result<u64> {
+        let start = try!(self.input.seek(SeekFrom::Current(0)));
+        let offset = index.checked_mul(self.ref_size as u64)
+            .and_then(|offset| start.checked_add(offset));
+        try!(self.input.seek(SeekFrom::Start(try!(offset.ok_or(ErrorKind::UnexpectedEof)))));
+
+        let obj = try!(sized_ints(&mut self.input, self.ref_size, 1))[0];
+        if obj >= self.offsets.len() as u64 {
+            return Err(ErrorKind::InvalidObjectReference(obj).into());
+        }
+        Ok(obj)
+    }
+}
+
+impl<R: Read + Seek> Iterator for BinaryEventReader<R> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        if self.finished {
+            return None;
+        }
+
+        match self.read_next() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(self.error(e)))
+            }
+        }
+    }
+}
+
+/// The type of the object a `Node` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// An array, or a set when sets are read as arrays
+    Array,
+    /// A dictionary
+    Dict,
+    /// A boolean value
+    Boolean,
+    /// A data value
+    Data,
+    /// A date value
+    DateTime,
+    /// A floating point value
+    Real,
+    /// An integer value
+    Integer,
+    /// A string value
+    String,
+    /// A UID value
+    Uid,
+    /// A null value
+    Null,
+}
+
+/// A binary property list that is decoded lazily, one object at a time, as
+/// it is navigated through `Node` handles.
+///
+/// The trailer and offset table are read once when the document is created.
+/// After that, only the objects that are actually reached are read from the
+/// input, which makes looking up a few values in a large file cheap.
+///
+/// ```rust
+/// use std::fs::File;
+/// use plist::{BinaryDocument, NodeKind};
+///
+/// let file = File::open("tests/types-binary.plist").unwrap();
+/// let document = BinaryDocument::new(file).unwrap();
+/// let root = document.root();
+/// assert_eq!(root.kind().unwrap(), NodeKind::Dict);
+///
+/// let string = root.get("String").unwrap().unwrap();
+/// assert_eq!(string.to_plist().unwrap().as_str(), Some("Hello"));
+/// ```
+#[derive(Debug)]
+pub struct BinaryDocument<R> {
+    // Nodes decode whole values with the same reader, restarted at their
+    // object, so that they are checked exactly as a stream of events is
+    reader: RefCell<BinaryEventReader<R>>,
+    root: u64,
+}
+
+impl<R: Read + Seek> BinaryDocument<R> {
+    /// Reads the trailer and offset table of a binary property list.
+    pub fn new(input: R) -> Result<BinaryDocument<R>> {
+        BinaryDocument::with_config(input, ReaderConfig::default())
+    }
+
+    /// Reads the trailer and offset table of a binary property list, whose
+    /// objects are read with the limits of `config`.
+    pub fn with_config(input: R, config: ReaderConfig) -> Result<BinaryDocument<R>> {
+        let mut reader = BinaryEventReader::with_config(input, config);
+        let root = try!(reader.header());
+        Ok(BinaryDocument {
+            reader: RefCell::new(reader),
+            root: root,
+        })
+    }
+
+    /// Returns the root object of the document.
+    pub fn root<'a>(&'a self) -> Node<'a, R> {
+        Node {
+            document: self,
+            obj: self.root,
+        }
+    }
+}
+
+/// A handle to an object of a `BinaryDocument`. Creating a node reads
+/// nothing, and each method reads only what it needs from the object.
+#[derive(Debug)]
+pub struct Node<'a, R: 'a> {
+    document: &'a BinaryDocument<R>,
+    obj: u64,
+}
+
+impl<'a, R> Clone for Node<'a, R> {
+    fn clone(&self) -> Node<'a, R> {
+        *self
+    }
+}
+
+impl<'a, R> Copy for Node<'a, R> {}
+
+impl<'a, R: Read + Seek> Node<'a, R> {
+    /// Returns the type of the object, failing with
+    /// `ErrorKind::ObjectNotSupported` for objects that cannot be read.
+    pub fn kind(&self) -> Result<NodeKind> {
+        let mut reader = self.document.reader.borrow_mut();
+        let reader = &mut *reader;
+        let marker = try!(seek_object(&mut reader.input, &reader.offsets, self.obj));
+        let kind = match marker >> 4 {
+            0x0 => {
+                match marker & 0xF {
+                    0x0 | 0xF => NodeKind::Null,
+                    0x8 | 0x9 => NodeKind::Boolean,
+                    _ => return Err(reader.at(ErrorKind::InvalidBoolean.into(), self.obj)),
+                }
+            }
+            0x1 => NodeKind::Integer,
+            0x2 => NodeKind::Real,
+            0x3 => NodeKind::DateTime,
+            0x4 => NodeKind::Data,
+            0x5 | 0x6 => NodeKind::String,
+            0x8 => NodeKind::Uid,
+            0xA => NodeKind::Array,
+            0xB | 0xC if reader.config.sets_as_arrays => NodeKind::Array,
+            0xD => NodeKind::Dict,
+            obj_type => {
+                let e = ErrorKind::ObjectNotSupported(obj_type).into();
+                return Err(reader.at(e, self.obj));
+            }
+        };
+        Ok(kind)
+    }
+
+    /// Returns the number of elements of an array or entries of a
+    /// dictionary, or `None` if the object is not a collection.
+    pub fn len(&self) -> Result<Option<usize>> {
+        let mut reader = self.document.reader.borrow_mut();
+        let collection = reader.collection_len(self.obj);
+        let collection = try!(collection.map_err(|e| reader.at(e, self.obj)));
+        Ok(collection.map(|(_, len)| len as usize))
+    }
+
+    /// Returns the value of `key` in a dictionary, or `None` if the object is
+    /// not a dictionary or does not contain the key.
+    ///
+    /// Every key is read until a match is found, but no values are read.
+    pub fn get(&self, key: &str) -> Result<Option<Node<'a, R>>> {
+        let found = {
+            let mut reader = self.document.reader.borrow_mut();
+            let reader = &mut *reader;
+            let len = match try!(reader.collection_len(self.obj)
+                .map_err(|e| reader.at(e, self.obj))) {
+                Some((true, len)) => len,
+                _ => return Ok(None),
+            };
+            let keys = sized_ints(&mut reader.input, reader.ref_size, len as usize);
+            let keys = try!(keys.map_err(|e| reader.at(e, self.obj)));
+
+            let mut found = None;
+            for (index, &k) in keys.iter().enumerate() {
+                let candidate = read_key(&mut reader.input, &reader.offsets, k, &reader.config);
+                if try!(candidate.map_err(|e| reader.at(e, k))) == key {
+                    found = Some(len + index as u64);
+                    break;
+                }
+            }
+            found
+        };
+        match found {
+            Some(index) => self.reference(index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the element at `index` of an array, or `None` if the object is
+    /// not an array or `index` is out of bounds.
+    pub fn index(&self, index: usize) -> Result<Option<Node<'a, R>>> {
+        let len = {
+            let mut reader = self.document.reader.borrow_mut();
+            let len = reader.collection_len(self.obj);
+            try!(len.map_err(|e| reader.at(e, self.obj)))
+        };
+        match len {
+            Some((false, len)) if (index as u64) < len => self.reference(index as u64).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads the object and everything it contains as a `Plist`.
+    ///
+    /// The object is read like a whole binary property list would be, so the
+    /// limits of the document's `ReaderConfig` apply to each call separately.
+    pub fn to_plist(&self) -> Result<Plist> {
+        let mut reader = self.document.reader.borrow_mut();
+        let config = reader.config;
+        reader.start_at(self.obj);
+        build(&mut *reader, false, &config)
+    }
+
+    fn reference(&self, index: u64) -> Result<Node<'a, R>> {
+        let mut reader = self.document.reader.borrow_mut();
+        let obj = reader.collection_len(self.obj).and_then(|_| reader.reference(index));
+        Ok(Node {
+            document: self.document,
+            obj: try!(obj.map_err(|e| reader.at(e, self.obj))),
+        })
+    }
+}
+
+// Borrows the next `len` bytes of a slice being read
+fn borrow_bytes<'a>(input: &mut Cursor<&'a [u8]>, len: u64) -> Result<&'a [u8]> {
+    let slice: &'a [u8] = *input.get_ref();
+    let start = input.position();
+    match start.checked_add(len) {
+        Some(end) if end <= slice.len() as u64 => Ok(&slice[start as usize..end as usize]),
+        _ => Err(ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+// UTF-16 strings cannot be borrowed, as they are converted to UTF-8
+fn borrow_string<'a>(input: &mut Cursor<&'a [u8]>,
+                     obj_type: u8,
+                     config: &ReaderConfig)
+                     -> Result<Cow<'a, str>> {
+    if obj_type != 0x5 {
+        return key(input, obj_type, config).map(Cow::Owned);
+    }
+    let len = try!(read_int(input));
+    try!(config.check_string(len));
+    Ok(Cow::Borrowed(try!(str::from_utf8(try!(borrow_bytes(input, len))))))
+}
+
+impl<'a> BinaryEventReader<Cursor<&'a [u8]>> {
+    // Reads the next value, borrowing its strings and data from the slice, or
+    // returns `None` at the end of the collection being read
+    fn next_ref(&mut self) -> Result<Option<PlistRef<'a>>> {
+        let obj_type = match try!(self.step()) {
+            Some(Step::StartArray(len)) => {
+                let mut array = Vec::with_capacity(len as usize);
+                while let Some(value) = try!(self.next_ref()) {
+                    array.push(value);
+                }
+                return Ok(Some(PlistRef::Array(array)));
+            }
+            Some(Step::StartDict(_)) => {
+                let mut dict = DictionaryRef::default();
+                while let Some(Step::Key(obj_type)) = try!(self.step()) {
+                    let key = try!(borrow_string(&mut self.input, obj_type, &self.config));
+                    let value = try!(self.next_ref());
+                    dict.insert(key, try!(value.ok_or(ErrorKind::UnexpectedEof)));
+                }
+                return Ok(Some(PlistRef::Dict(dict)));
+            }
+            Some(Step::Value(obj_type)) => obj_type,
+            Some(Step::Key(_)) |
+            Some(Step::EndCollection) |
+            None => return Ok(None),
+        };
+
+        let input = &mut self.input;
+        let value = match obj_type {
+            0x4 => {
+                let len = try!(read_int(input));
+                try!(self.config.check_data(len));
+                PlistRef::Data(Cow::Borrowed(try!(borrow_bytes(input, len))))
+            }
+            0x5 | 0x6 => PlistRef::String(try!(borrow_string(input, obj_type, &self.config))),
+            _ => {
+                let event = scalar(input, obj_type, &self.config);
+                PlistRef::from(try!(build(&mut iter::once(event), false, &self.config)))
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+pub fn from_binary_slice<'a>(input: &'a [u8], config: &ReaderConfig) -> Result<PlistRef<'a>> {
+    let mut reader = BinaryEventReader::with_config(Cursor::new(input), *config);
+    match reader.next_ref() {
+        Ok(plist) => plist.ok_or(ErrorKind::UnexpectedEof.into()),
+        Err(e) => Err(reader.error(e)),
+    }
+}
+
+pub fn from_binary_reader<R: Read + Seek>(input: &mut R, config: &ReaderConfig) -> Result<Plist> {
+    build(&mut BinaryEventReader::with_config(input, *config), false, config)
 }
@@ -1,21 +1,253 @@
+use std::hash::BuildHasherDefault;
 use std::io::{Read, Seek, SeekFrom};
+use fnv::FnvHasher;
+use indexmap::IndexMap;
 
+use date::Date;
+use integer::Integer;
 use plist::Plist;
-use result::{Result, Error};
+use result::{Result, ErrorKind};
 
+pub mod ascii;
 pub mod binary;
 pub mod xml;
 
+use self::ascii::from_ascii_reader;
 use self::binary::from_binary_reader;
 use self::xml::from_xml_reader;
 
-pub fn from_reader<R: Read + Seek>(input: &mut R) -> Result<Plist> {
-    match from_binary_reader(input) {
+/// Limits and options applied while reading a property list. The limits guard
+/// against untrusted input exhausting memory or the stack.
+///
+/// Binary property lists store the length of each object, so their lengths
+/// are checked before any memory is allocated for it. XML and ASCII values
+/// are checked once they have been read, which bounds the size of the
+/// resulting `Plist` but not of the text being parsed. By default only the
+/// nesting depth is limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReaderConfig {
+    /// The maximum number of values decoded, including dictionary keys.
+    ///
+    /// An object that a binary property list references more than once is
+    /// counted every time it is decoded, so shared references cannot expand
+    /// a small file into an arbitrarily large tree.
+    pub max_objects: usize,
+    /// The maximum length of a string or dictionary key, in bytes, or in code
+    /// units for UTF-16 strings in binary property lists.
+    pub max_string_length: usize,
+    /// The maximum length of a data value, in bytes.
+    pub max_data_length: usize,
+    /// The maximum number of elements in an array or entries in a dictionary.
+    pub max_collection_length: usize,
+    /// The maximum number of arrays and dictionaries nested in each other.
+    pub max_depth: usize,
+    /// Whether the set and ordered set objects of binary property lists are
+    /// read as arrays, rather than failing with `ErrorKind::ObjectNotSupported`.
+    ///
+    /// Sets have no XML or ASCII representation, so reading them as arrays
+    /// means they are written back as arrays in every format.
+    pub sets_as_arrays: bool,
+}
+
+impl Default for ReaderConfig {
+    fn default() -> ReaderConfig {
+        ReaderConfig {
+            max_objects: usize::max_value(),
+            max_string_length: usize::max_value(),
+            max_data_length: usize::max_value(),
+            max_collection_length: usize::max_value(),
+            max_depth: 512,
+            sets_as_arrays: true,
+        }
+    }
+}
+
+impl ReaderConfig {
+    #[inline]
+    fn check(len: u64, max: usize, kind: ErrorKind) -> Result<()> {
+        if len > max as u64 { Err(kind.into()) } else { Ok(()) }
+    }
+
+    fn check_objects(&self, count: u64) -> Result<()> {
+        ReaderConfig::check(count, self.max_objects, ErrorKind::TooManyObjects)
+    }
+
+    fn check_string(&self, len: u64) -> Result<()> {
+        ReaderConfig::check(len, self.max_string_length, ErrorKind::StringTooLong)
+    }
+
+    fn check_data(&self, len: u64) -> Result<()> {
+        ReaderConfig::check(len, self.max_data_length, ErrorKind::DataTooLong)
+    }
+
+    fn check_collection(&self, len: u64) -> Result<()> {
+        ReaderConfig::check(len, self.max_collection_length, ErrorKind::CollectionTooLong)
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<()> {
+        if depth > self.max_depth { Err(ErrorKind::MaxDepthExceeded.into()) } else { Ok(()) }
+    }
+}
+
+/// An event encountered while reading a property list.
+///
+/// Collections are delimited by a start event, which carries the number of
+/// elements when the format records it, and an `EndCollection` event.
+/// Dictionary values are each preceded by a `Key` event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The start of an array, with its length if known
+    StartArray(Option<u64>),
+    /// The start of a dictionary, with its number of entries if known
+    StartDict(Option<u64>),
+    /// The end of the most recently started array or dictionary
+    EndCollection,
+    /// A dictionary key, followed by the events of its value
+    Key(String),
+    /// A boolean value
+    Boolean(bool),
+    /// A data value
+    Data(Vec<u8>),
+    /// A date value
+    DateTime(Date),
+    /// A floating point value
+    Real(f64),
+    /// An integer value
+    Integer(Integer),
+    /// A string value
+    String(String),
+    /// A UID value, which is only encoded directly by binary property lists
+    Uid(u64),
+    /// A null value, which is only encoded by binary property lists
+    Null,
+}
+
+// XML property lists represent a UID as a dictionary with a single CF$UID
+// integer
+fn dict_uid(plist: Plist) -> Plist {
+    if let Plist::Dict(ref dict) = plist {
+        if let (1, Some(&Plist::Integer(i))) = (dict.len(), dict.get("CF$UID")) {
+            if let Some(uid) = i.as_unsigned() {
+                return Plist::Uid(uid);
+            }
+        }
+    }
+    plist
+}
+
+/// Builds a `Plist` from the events of a single value, without reading past
+/// the end of that value, optionally converting `CF$UID` dictionaries to UIDs.
+///
+/// Every value and key is counted against `config.max_objects`, whatever
+/// the events come from.
+pub fn build<I>(events: &mut I, dict_uids: bool, config: &ReaderConfig) -> Result<Plist>
+    where I: Iterator<Item = Result<Event>>
+{
+    let mut stack: Vec<(Plist, Option<String>)> = Vec::new();
+    let mut key = None;
+    let mut objects = 0;
+
+    while let Some(event) = events.next() {
+        let event = try!(event);
+        if event != Event::EndCollection {
+            objects += 1;
+            try!(config.check_objects(objects));
+        }
+
+        let value = match event {
+            Event::StartArray(len) => {
+                let array = Vec::with_capacity(len.unwrap_or(0) as usize);
+                stack.push((Plist::Array(array), key.take()));
+                continue;
+            }
+            Event::StartDict(len) => {
+                let fnv = BuildHasherDefault::<FnvHasher>::default();
+                let dict = IndexMap::with_capacity_and_hasher(len.unwrap_or(0) as usize, fnv);
+                stack.push((Plist::Dict(dict), key.take()));
+                continue;
+            }
+            Event::Key(k) => {
+                key = Some(k);
+                continue;
+            }
+            Event::EndCollection => {
+                match stack.pop() {
+                    Some((collection, k)) => {
+                        key = k;
+                        if dict_uids { dict_uid(collection) } else { collection }
+                    }
+                    None => return Err(ErrorKind::UnexpectedEof.into()),
+                }
+            }
+            Event::Boolean(b) => Plist::Boolean(b),
+            Event::Data(d) => Plist::Data(d),
+            Event::DateTime(d) => Plist::DateTime(d),
+            Event::Real(r) => Plist::Real(r),
+            Event::Integer(i) => Plist::Integer(i),
+            Event::String(s) => Plist::String(s),
+            Event::Uid(u) => Plist::Uid(u),
+            Event::Null => Plist::Null,
+        };
+
+        match stack.last_mut() {
+            Some(&mut (Plist::Array(ref mut array), _)) => array.push(value),
+            Some(&mut (Plist::Dict(ref mut dict), _)) => {
+                match key.take() {
+                    Some(k) => dict.insert(k, value),
+                    None => return Err(ErrorKind::InvalidKeyObject.into()),
+                };
+            }
+            Some(_) => unreachable!(),
+            None => return Ok(value),
+        }
+    }
+
+    Err(ErrorKind::UnexpectedEof.into())
+}
+
+fn is_xml<R: Read>(input: &mut R) -> Result<bool> {
+    let mut buf = [0; 1];
+
+    // Skip any byte order mark and leading whitespace
+    loop {
+        if try!(input.read(&mut buf)) == 0 {
+            return Ok(false);
+        }
+        match buf[0] {
+            b' ' | b'\t' | b'\r' | b'\n' | 0xEF | 0xBB | 0xBF => (),
+            b'<' => break,
+            _ => return Ok(false),
+        }
+    }
+
+    // ASCII data objects also start with a '<', but are followed by hex digits
+    if try!(input.read(&mut buf)) == 0 {
+        return Ok(false);
+    }
+    Ok(match buf[0] {
+        b'?' | b'!' | b'p' => true,
+        _ => false,
+    })
+}
+
+pub fn from_reader<R: Read + Seek>(input: &mut R, config: &ReaderConfig) -> Result<Plist> {
+    match from_binary_reader(input, config) {
         Ok(p) => return Ok(p),
-        Err(Error::InvalidMagicBytes) => (),
-        Err(e) => return Err(e),
+        Err(e) => {
+            match *e.kind() {
+                ErrorKind::InvalidMagicBytes => (),
+                _ => return Err(e),
+            }
+        }
     };
 
     try!(input.seek(SeekFrom::Start(0)));
-    from_xml_reader(input)
+    let xml = try!(is_xml(input));
+
+    try!(input.seek(SeekFrom::Start(0)));
+    if xml {
+        from_xml_reader(input, config)
+    } else {
+        from_ascii_reader(input, config)
+    }
 }
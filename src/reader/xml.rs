@@ -1,241 +1,320 @@
-use std::collections::HashMap;
-use std::hash::BuildHasherDefault;
+use std::fmt;
 use std::io::Read;
-use std::iter::Peekable;
-use std::time::{Duration, UNIX_EPOCH};
-use chrono::DateTime;
-use fnv::FnvHasher;
 use rustc_serialize::base64::FromBase64;
-use xml::reader::{EventReader, ParserConfig, Result as XmlResult, XmlEvent};
+use xml::common::Position as XmlPosition;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
 
+use date::Date;
+use integer::Integer;
 use plist::Plist;
-use result::{Result, Error};
-
-fn xml_event<R: Iterator<Item = XmlResult<XmlEvent>>, P>(input: &mut Peekable<R>,
-                                                         mut predicate: P)
-                                                         -> Result<()>
-    where P: FnMut(&XmlEvent) -> Option<bool>
-{
-    match input.next() {
-        Some(Ok(e)) => {
-            match predicate(&e) {
-                Some(true) => Ok(()),
-                Some(false) => Err(Error::UnexpectedXmlEvent(e)),
-                None => xml_event(input, predicate),
+use result::{Result, Error, ErrorKind, PathSegment, Position, with_path, with_position,
+             with_source};
+use super::{Event, ReaderConfig, build};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Collection {
+    Array,
+    Dict { expect_key: bool },
+}
+
+/// An iterator over the events of an XML property list, which reads the
+/// document incrementally instead of building the whole `Plist` in memory.
+pub struct XmlEventReader<R: Read> {
+    reader: EventReader<R>,
+    config: ReaderConfig,
+    // Each open collection with the number of elements read so far, and the
+    // key of the current value of a dictionary
+    stack: Vec<(Collection, u64, Option<String>)>,
+    objects: u64,
+    started: bool,
+    root_read: bool,
+    finished: bool,
+}
+
+impl<R: Read> XmlEventReader<R> {
+    /// Creates an event reader over an XML property list.
+    pub fn new(input: R) -> XmlEventReader<R> {
+        XmlEventReader::with_config(input, ReaderConfig::default())
+    }
+
+    /// Creates an event reader over an XML property list, which enforces the
+    /// limits of `config`.
+    pub fn with_config(input: R, config: ReaderConfig) -> XmlEventReader<R> {
+        let parser_config = ParserConfig {
+            trim_whitespace: false,
+            whitespace_to_characters: true,
+            cdata_to_characters: false,
+            ignore_comments: true,
+            coalesce_characters: true,
+        };
+
+        XmlEventReader {
+            reader: EventReader::new_with_config(input, parser_config),
+            config: config,
+            stack: Vec::new(),
+            objects: 0,
+            started: false,
+            root_read: false,
+            finished: false,
+        }
+    }
+
+    fn xml_event(&mut self) -> Result<XmlEvent> {
+        match try!(self.reader.next().map_err(|e| with_source(ErrorKind::InvalidXml, e))) {
+            XmlEvent::EndDocument => Err(ErrorKind::UnexpectedXmlEof.into()),
+            e => Ok(e),
+        }
+    }
+
+    fn xml_start(&mut self, local_name: &str) -> Result<()> {
+        loop {
+            match try!(self.xml_event()) {
+                XmlEvent::StartElement { ref name, .. } if &name.local_name[..] == local_name => {
+                    return Ok(())
+                }
+                XmlEvent::Characters(_) => (),
+                e => return Err(unexpected(e)),
             }
         }
-        Some(Err(e)) => Err(Error::from(e)),
-        None => Err(Error::UnexpectedXmlEof),
     }
-}
 
-fn xml_start<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>,
-                                                      local_name: &str)
-                                                      -> Result<()> {
-    xml_event(input, |e| {
-        match *e {
-            XmlEvent::StartElement { ref name, .. } => Some(&name.local_name[..] == local_name),
-            XmlEvent::Characters(ref _string) => None,
-            _ => Some(false),
+    fn xml_end(&mut self, local_name: &str) -> Result<String> {
+        let mut string = None;
+        loop {
+            match try!(self.xml_event()) {
+                XmlEvent::EndElement { ref name } if &name.local_name[..] == local_name => break,
+                XmlEvent::Characters(s) => string = Some(s),
+                e => return Err(unexpected(e)),
+            }
         }
-    })
-}
 
-fn xml_end<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>,
-                                                    local_name: &str)
-                                                    -> Result<String> {
-    let mut string = None;
-    try!(xml_event(input, |e| {
-        match *e {
-            XmlEvent::EndElement { ref name } => Some(&name.local_name[..] == local_name),
-            XmlEvent::Characters(ref s) => {
-                // TODO: Don't clone
-                string = Some(s.clone());
-                None
+        match string {
+            Some(s) => Ok(s),
+            None => Ok("".to_string()),
+        }
+    }
+
+    fn xml_string(&mut self, local_name: &str) -> Result<String> {
+        let string = try!(self.xml_end(local_name));
+        try!(self.config.check_string(string.len() as u64));
+        Ok(string)
+    }
+
+    fn xml_collection(&mut self, collection: Collection) -> Result<()> {
+        try!(self.config.check_depth(self.stack.len() + 1));
+        self.stack.push((collection, 0, None));
+        Ok(())
+    }
+
+    fn xml_value(&mut self, local_name: &str) -> Result<Event> {
+        Ok(match local_name {
+            "true" => {
+                try!(self.xml_end(local_name));
+                Event::Boolean(true)
+            }
+            "false" => {
+                try!(self.xml_end(local_name));
+                Event::Boolean(false)
+            }
+            "integer" => Event::Integer(try!(xml_integer(&try!(self.xml_end(local_name))))),
+            "real" => Event::Real(try!(try!(self.xml_end(local_name)).parse())),
+            "date" => Event::DateTime(try!(Date::from_rfc3339(&try!(self.xml_end(local_name))))),
+            "data" => {
+                let data = try!(self.xml_end(local_name));
+                Event::Data(try!(xml_data(&data, &self.config)))
+            }
+            "string" => Event::String(try!(self.xml_string(local_name))),
+            "array" => {
+                try!(self.xml_collection(Collection::Array));
+                Event::StartArray(None)
+            }
+            "dict" => {
+                try!(self.xml_collection(Collection::Dict { expect_key: true }));
+                Event::StartDict(None)
+            }
+            s => return Err(ErrorKind::XmlObjectNotSupported(s.to_string()).into()),
+        })
+    }
+
+    fn count_object(&mut self, is_key: bool) -> Result<()> {
+        self.objects += 1;
+        try!(self.config.check_objects(self.objects));
+
+        // Dictionary entries are counted by their keys
+        if let Some(&mut (collection, ref mut len, _)) = self.stack.last_mut() {
+            if is_key || collection == Collection::Array {
+                *len += 1;
+                try!(self.config.check_collection(*len));
             }
-            _ => Some(false),
         }
-    }));
+        Ok(())
+    }
 
-    match string {
-        Some(s) => Ok(s),
-        None => Ok("".to_string()),
+    // The keys and indices of the values currently being read
+    fn path(&self) -> Vec<PathSegment> {
+        self.stack
+            .iter()
+            .filter_map(|&(collection, len, ref key)| match collection {
+                Collection::Array if len > 0 => Some(PathSegment::Index(len - 1)),
+                Collection::Array => None,
+                Collection::Dict { .. } => key.clone().map(PathSegment::Key),
+            })
+            .collect()
     }
-}
 
-fn xml_content<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>,
-                                                        local_name: &str)
-                                                        -> Result<String> {
-    try!(xml_start(input, local_name));
-    xml_end(input, local_name)
-}
+    fn read_next(&mut self) -> Result<Option<Event>> {
+        if !self.started {
+            self.started = true;
+            match try!(self.xml_event()) {
+                XmlEvent::StartDocument { .. } => (),
+                e => return Err(unexpected(e)),
+            }
+            try!(self.xml_start("plist"));
+        }
 
-fn xml_boolean<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    match input.next() {
-        Some(Ok(e)) => {
-            match e {
+        loop {
+            let event = try!(self.xml_event());
+            match event {
+                XmlEvent::Characters(_) => continue,
                 XmlEvent::StartElement { ref name, .. } => {
-                    match &name.local_name[..] {
-                        "true" => {
-                            try!(xml_end(input, "true"));
-                            return Ok(Plist::Boolean(true));
+                    // Dictionaries alternate between keys and values, and the
+                    // plist element contains exactly one value
+                    let is_key = &name.local_name[..] == "key";
+                    let expected = match self.stack.last_mut() {
+                        Some(&mut (Collection::Dict { ref mut expect_key }, _, ref mut key)) if
+                            *expect_key == is_key => {
+                            *expect_key = !is_key;
+                            if is_key {
+                                *key = None;
+                            }
+                            true
                         }
-                        "false" => {
-                            try!(xml_end(input, "false"));
-                            return Ok(Plist::Boolean(false));
+                        Some(&mut (Collection::Dict { .. }, _, _)) => false,
+                        Some(&mut (Collection::Array, _, _)) => !is_key,
+                        None => !is_key && !self.root_read,
+                    };
+
+                    if expected {
+                        self.root_read = true;
+                        try!(self.count_object(is_key));
+                        return if is_key {
+                            let key = try!(self.xml_string("key"));
+                            if let Some(&mut (_, _, ref mut k)) = self.stack.last_mut() {
+                                *k = Some(key.clone());
+                            }
+                            Ok(Some(Event::Key(key)))
+                        } else {
+                            self.xml_value(&name.local_name).map(Some)
+                        };
+                    }
+                }
+                XmlEvent::EndElement { ref name } => {
+                    match (&name.local_name[..], self.stack.last().map(|c| c.0)) {
+                        ("array", Some(Collection::Array)) |
+                        ("dict", Some(Collection::Dict { expect_key: true })) => {
+                            self.stack.pop();
+                            return Ok(Some(Event::EndCollection));
                         }
+                        ("plist", None) if self.root_read => return Ok(None),
                         _ => (),
                     }
                 }
-                XmlEvent::Characters(ref _string) => return xml_boolean(input),
                 _ => (),
-            };
-            Err(Error::UnexpectedXmlEvent(e))
+            }
+            return Err(unexpected(event));
         }
-        Some(Err(e)) => Err(Error::from(e)),
-        None => Err(Error::UnexpectedXmlEof),
     }
 }
 
-fn xml_integer<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    let string = try!(xml_content(input, "integer"));
-    let integer = try!(i64::from_str_radix(string.as_ref(), 10));
-    Ok(Plist::Integer(integer))
-}
-
-fn xml_real<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    let string = try!(xml_content(input, "real"));
-    let real = try!(string.parse());
-    Ok(Plist::Real(real))
-}
-
-fn xml_date<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    let string = try!(xml_content(input, "date"));
-    let secs = try!(DateTime::parse_from_rfc3339(string.as_ref())).timestamp() as u64;
-    Ok(Plist::DateTime(UNIX_EPOCH + Duration::from_secs(secs)))
-}
-
-fn xml_data<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    let string = try!(xml_content(input, "data"));
-    let stripped = string.split_whitespace()
-        .fold(String::with_capacity(string.len()), |mut x, y| {
-            x.push_str(y);
-            x
-        });
-    Ok(Plist::Data(try!(stripped.from_base64())))
-}
+impl<R: Read> Iterator for XmlEventReader<R> {
+    type Item = Result<Event>;
 
-fn xml_string<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    Ok(Plist::String(try!(xml_content(input, "string"))))
-}
+    fn next(&mut self) -> Option<Result<Event>> {
+        if self.finished {
+            return None;
+        }
 
-fn xml_array<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    try!(xml_start(input, "array"));
-
-    let mut array = Vec::new();
-    loop {
-        match xml_object(input) {
-            Ok(o) => array.push(o),
-            Err(Error::UnexpectedXmlEvent(e)) => {
-                let valid = if let XmlEvent::EndElement { ref name } = e {
-                    &name.local_name[..] == "array"
-                } else {
-                    false
-                };
-                if valid {
-                    break;
-                } else {
-                    return Err(Error::UnexpectedXmlEvent(e));
-                }
+        match self.read_next() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.finished = true;
+                None
             }
-            Err(e) => return Err(e),
-        };
+            Err(e) => {
+                self.finished = true;
+                let position = self.reader.position();
+                let e = with_path(e, self.path());
+                Some(Err(with_position(e,
+                                       Position::Text {
+                                           line: position.row + 1,
+                                           column: position.column + 1,
+                                       })))
+            }
+        }
     }
-
-    Ok(Plist::Array(array))
 }
 
-fn xml_dict<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    try!(xml_start(input, "dict"));
-
-    let fnv = BuildHasherDefault::<FnvHasher>::default();
-    let mut dict = HashMap::with_hasher(fnv);
-    loop {
-        match xml_content(input, "key") {
-            Ok(key) => {
-                let value = try!(xml_object(input));
-                dict.insert(key, value);
-            }
-            Err(Error::UnexpectedXmlEvent(e)) => {
-                let valid = if let XmlEvent::EndElement { ref name } = e {
-                    &name.local_name[..] == "dict"
-                } else {
-                    false
-                };
-                if valid {
-                    break;
-                } else {
-                    return Err(Error::UnexpectedXmlEvent(e));
-                }
-            }
-            Err(e) => return Err(e),
-        };
+impl<R: Read> fmt::Debug for XmlEventReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("XmlEventReader")
+            .field("config", &self.config)
+            .field("stack", &self.stack)
+            .field("objects", &self.objects)
+            .field("started", &self.started)
+            .field("root_read", &self.root_read)
+            .field("finished", &self.finished)
+            .finish()
     }
+}
 
-    Ok(Plist::Dict(dict))
+// Events are described by their debug representation, so that errors do not
+// expose the XML parser's types
+fn unexpected(event: XmlEvent) -> Error {
+    ErrorKind::UnexpectedXmlEvent(format!("{:?}", event)).into()
 }
 
-fn xml_object<R: Iterator<Item = XmlResult<XmlEvent>>>(input: &mut Peekable<R>) -> Result<Plist> {
-    let object_func: Option<fn(&mut Peekable<R>) -> Result<Plist>> = match input.peek() {
-        Some(&Ok(XmlEvent::StartElement { ref name, .. })) => {
-            Some(match &name.local_name[..] {
-                "true" => xml_boolean,
-                "false" => xml_boolean,
-                "integer" => xml_integer,
-                "real" => xml_real,
-                "date" => xml_date,
-                "data" => xml_data,
-                "string" => xml_string,
-                "array" => xml_array,
-                "dict" => xml_dict,
-                s => return Err(Error::XmlObjectNotSupported(s.to_string())),
-            })
-        }
-        _ => None,
+fn xml_integer(string: &str) -> Result<Integer> {
+    // Like CFPropertyList, allow surrounding whitespace, a sign and hex digits
+    let string = string.trim();
+    let (negative, unsigned) = match string.chars().next() {
+        Some('-') => (true, &string[1..]),
+        Some('+') => (false, &string[1..]),
+        _ => (false, string),
+    };
+    let (radix, digits) = if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+        (16, &unsigned[2..])
+    } else {
+        (10, unsigned)
     };
 
-    if let Some(func) = object_func {
-        return func(input);
-    }
-
-    match input.next() {
-        Some(Ok(XmlEvent::Characters(_string))) => xml_object(input),
-        Some(Ok(e)) => Err(Error::UnexpectedXmlEvent(e)),
-        Some(Err(e)) => Err(Error::from(e)),
-        None => Err(Error::UnexpectedXmlEof),
+    // Parsing with the sign included rejects a second sign among the digits
+    if negative {
+        Ok(Integer::from(try!(i64::from_str_radix(&format!("-{}", digits), radix))))
+    } else {
+        Ok(Integer::from(try!(u64::from_str_radix(&format!("+{}", digits), radix))))
     }
 }
 
+fn xml_data(string: &str, config: &ReaderConfig) -> Result<Vec<u8>> {
+    // Every four base64 characters decode to at most three bytes
+    let encoded_len = string.bytes().filter(|b| !b.is_ascii_whitespace()).count() as u64;
+    try!(config.check_data(encoded_len / 4 * 3));
 
-pub fn from_xml_reader<R: Read>(input: &mut R) -> Result<Plist> {
-    let config = ParserConfig {
-        trim_whitespace: false,
-        whitespace_to_characters: true,
-        cdata_to_characters: false,
-        ignore_comments: true,
-        coalesce_characters: true,
-    };
-    let mut events = EventReader::new_with_config(input, config).into_iter().peekable();
+    let stripped = string.split_whitespace()
+        .fold(String::with_capacity(string.len()), |mut x, y| {
+            x.push_str(y);
+            x
+        });
+    stripped.from_base64().map_err(|e| with_source(ErrorKind::InvalidData, e))
+}
 
-    try!(xml_event(&mut events, |e| {
-        Some(if let &XmlEvent::StartDocument { .. } = e {
-            true
-        } else {
-            false
-        })
-    }));
-    try!(xml_start(&mut events, "plist"));
-    let object = try!(xml_object(&mut events));
-    try!(xml_end(&mut events, "plist"));
-    Ok(object)
+pub fn from_xml_reader<R: Read>(input: &mut R, config: &ReaderConfig) -> Result<Plist> {
+    let mut events = XmlEventReader::with_config(input, *config);
+    let plist = try!(build(&mut events, true, config));
+
+    // Anything other than the end of the plist element is an error
+    match events.next() {
+        Some(Err(e)) => Err(e),
+        _ => Ok(plist),
+    }
 }
@@ -6,13 +6,136 @@ use std::num;
 use std::result;
 use std::str;
 use std::string;
-use chrono::format;
-use rustc_serialize::base64;
-use xml::reader;
+#[cfg(feature = "serde")]
+use serde::{de, ser};
 
-/// The errors that can occur when parsing a property list.
+/// An error that occurred when reading or writing a property list, along with
+/// the position in the document and the path of keys and indices to the value
+/// that it occurred at.
+///
+/// The underlying error, such as an I/O error, is available from `source`.
 #[derive(Debug)]
-pub enum Error {
+pub struct Error {
+    kind: ErrorKind,
+    position: Option<Position>,
+    path: Vec<PathSegment>,
+    source: Option<Box<dyn error::Error + Send + Sync>>,
+}
+
+/// The position in a property list document that an error occurred at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// A line and column in an XML or ASCII property list, both counted from
+    /// 1.
+    Text {
+        /// The line number
+        line: u64,
+        /// The column number
+        column: u64,
+    },
+    /// An object in a binary property list, and the byte offset it starts at.
+    /// Errors in the header or trailer have no object.
+    Binary {
+        /// The index of the object in the offset table
+        object: Option<u64>,
+        /// The byte offset of the object, header or trailer
+        offset: u64,
+    },
+}
+
+/// A dictionary key or array index in the path to a value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// The key of a dictionary value
+    Key(String),
+    /// The index of an array element, counted from 0
+    Index(u64),
+}
+
+impl PathSegment {
+    // Keys that could be mistaken for other segments are quoted
+    fn is_quoted(&self) -> bool {
+        match *self {
+            PathSegment::Key(ref k) => {
+                k.is_empty() || k.contains(|c| c == '.' || c == '[' || c == ']' || c == '"')
+            }
+            PathSegment::Index(_) => false,
+        }
+    }
+}
+
+/// Keys are displayed as they are, unless they are empty or contain `.`, `[`,
+/// `]` or `"`, in which case they are quoted and escaped in brackets, like
+/// `["com.apple.security"]`. Indices are displayed in brackets, like `[2]`.
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathSegment::Key(ref k) if self.is_quoted() => write!(f, "[{:?}]", k),
+            PathSegment::Key(ref k) => write!(f, "{}", k),
+            PathSegment::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the kind of error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Converts the error into its kind, discarding the rest of its context.
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the position in the document the error occurred at, if it
+    /// occurred while reading a document.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Returns the keys and indices leading from the root of the document to
+    /// the value the error occurred at, which is empty for errors outside of
+    /// any array or dictionary.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+}
+
+/// Creates an error of the given kind, caused by an underlying error.
+pub fn with_source<E>(kind: ErrorKind, source: E) -> Error
+    where E: error::Error + Send + Sync + 'static
+{
+    let mut error = Error::from(kind);
+    error.source = Some(Box::new(source));
+    error
+}
+
+/// Attaches a position to an error, unless it already has one from a more
+/// precise location.
+pub fn with_position(mut error: Error, position: Position) -> Error {
+    if error.position.is_none() {
+        error.position = Some(position);
+    }
+    error
+}
+
+/// Attaches the path of the value being read to an error, unless it already
+/// has one.
+pub fn with_path(mut error: Error, path: Vec<PathSegment>) -> Error {
+    if error.path.is_empty() {
+        error.path = path;
+    }
+    error
+}
+
+/// The kinds of errors that can occur when reading or writing a property list.
+///
+/// More kinds may be added in future versions, so matches on this type need a
+/// wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
     /// The binary property list does not have valid magic bytes.
     InvalidMagicBytes,
     /// The binary property list trailer does not contain valid values.
@@ -29,65 +152,99 @@ pub enum Error {
     InvalidIntegerSize,
     /// The binary property list has an unsupported object type.
     ObjectNotSupported(u8),
+    /// The binary property list references an object that is not in its
+    /// offset table.
+    InvalidObjectReference(u64),
+    /// The binary property list has an object offset that is not between the
+    /// header and the offset table.
+    InvalidObjectOffset(u64),
+    /// The binary property list has an array or dictionary that contains
+    /// itself, directly or through other collections.
+    RecursiveObject(u64),
+    /// The property list has arrays or dictionaries nested deeper than the
+    /// configured maximum depth.
+    MaxDepthExceeded,
+    /// The property list has more objects than the configured maximum.
+    TooManyObjects,
+    /// The property list has a string longer than the configured maximum.
+    StringTooLong,
+    /// The property list has a data value longer than the configured maximum.
+    DataTooLong,
+    /// The property list has an array or dictionary with more elements than
+    /// the configured maximum.
+    CollectionTooLong,
 
     /// The XML property list encountered an early end of the document.
     UnexpectedXmlEof,
-    /// The XML property list contains an unexpected XML event.
-    UnexpectedXmlEvent(reader::XmlEvent),
+    /// The XML property list contains an unexpected XML event, which is
+    /// described by the string.
+    UnexpectedXmlEvent(String),
     /// The XML property list contains an unsupported object type.
     XmlObjectNotSupported(String),
     /// The XML property list contains invalid XML.
-    XmlError(reader::Error),
+    InvalidXml,
+
+    /// The ASCII property list encountered an early end of the document.
+    UnexpectedAsciiEof,
+    /// The ASCII property list contains an unexpected character.
+    UnexpectedAsciiCharacter(char),
+
+    /// The property list ends before a complete value has been read.
+    UnexpectedEof,
 
-    /// The reader experienced an I/O error.
-    IoError(io::Error),
+    /// The property list is not a valid `NSKeyedArchiver` archive.
+    InvalidArchive(String),
+    /// The property list contains a null value, which can only be written as a
+    /// binary property list.
+    NullNotSupported,
+    /// The property list contains a date that cannot be represented in the
+    /// requested format or type.
+    DateOutOfRange,
+    /// The property list value cannot be converted to the named Rust type.
+    UnexpectedType(&'static str),
+    /// The property list integer does not fit in the named Rust type.
+    IntegerOutOfRange(&'static str),
+
+    /// The reader or writer experienced an I/O error.
+    Io,
     /// The XML property list contains an invalid integer value
-    IntError(num::ParseIntError),
+    InvalidInteger,
     /// The XML property list contains an invalid float value
-    FloatError(num::ParseFloatError),
+    InvalidReal,
     /// The XML property list contains an invalid date value
-    DateError(format::ParseError),
-    /// The XML property list contains an invalid data value
-    Base64Error(base64::FromBase64Error),
+    InvalidDate,
+    /// The XML property list contains an invalid base64 data value
+    InvalidData,
     /// The property list contains an invalid UTF-8 string value
-    Utf8Error(str::Utf8Error),
+    InvalidUtf8,
     /// The property list contains an invalid UTF-16 string value
-    Utf16Error(string::FromUtf16Error),
+    InvalidUtf16,
+
+    /// A value could not be serialized to or deserialized from a property list.
+    Serde(String),
 }
 
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Error {
-        Error::IoError(error)
+        with_source(ErrorKind::Io, error)
     }
 }
 
 impl From<num::ParseIntError> for Error {
     fn from(error: num::ParseIntError) -> Error {
-        Error::IntError(error)
+        with_source(ErrorKind::InvalidInteger, error)
     }
 }
 
 impl From<num::ParseFloatError> for Error {
     fn from(error: num::ParseFloatError) -> Error {
-        Error::FloatError(error)
-    }
-}
-
-impl From<format::ParseError> for Error {
-    fn from(error: format::ParseError) -> Error {
-        Error::DateError(error)
-    }
-}
-
-impl From<base64::FromBase64Error> for Error {
-    fn from(error: base64::FromBase64Error) -> Error {
-        Error::Base64Error(error)
+        with_source(ErrorKind::InvalidReal, error)
     }
 }
 
 impl From<str::Utf8Error> for Error {
     fn from(error: str::Utf8Error) -> Error {
-        Error::Utf8Error(error)
+        with_source(ErrorKind::InvalidUtf8, error)
     }
 }
 
@@ -99,68 +256,122 @@ impl From<string::FromUtf8Error> for Error {
 
 impl From<string::FromUtf16Error> for Error {
     fn from(error: string::FromUtf16Error) -> Error {
-        Error::Utf16Error(error)
+        with_source(ErrorKind::InvalidUtf16, error)
     }
 }
 
-impl From<reader::Error> for Error {
-    fn from(error: reader::Error) -> Error {
-        Error::XmlError(error)
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error {
+            kind: kind,
+            position: None,
+            path: Vec::new(),
+            source: None,
+        }
     }
 }
 
 impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.path.iter().enumerate() {
+            match *segment {
+                PathSegment::Key(_) if i > 0 && !segment.is_quoted() => {
+                    try!(write!(f, ".{}", segment))
+                }
+                _ => try!(write!(f, "{}", segment)),
+            }
+        }
+        if !self.path.is_empty() {
+            try!(write!(f, ": "));
+        }
+
+        try!(self.kind.fmt(f));
+        match self.position {
+            Some(Position::Text { line, column }) => {
+                write!(f, " at line {}, column {}", line, column)
+            }
+            Some(Position::Binary { object: Some(object), offset }) => {
+                write!(f, " at object {} (offset {})", object, offset)
+            }
+            Some(Position::Binary { object: None, offset }) => write!(f, " at offset {}", offset),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::InvalidMagicBytes => write!(f, "Magic bytes are incorrect"),
-            Error::InvalidTrailer => write!(f, "Trailer is invalid"),
-            Error::VersionNotSupported(Some(ref s)) => write!(f, "Version {} not supported", s),
-            Error::VersionNotSupported(None) => write!(f, "Version not supported"),
-            Error::InvalidKeyObject => write!(f, "Key object is not a string"),
-            Error::InvalidBoolean => write!(f, "Boolean object has an invalid value"),
-            Error::InvalidIntegerSize => write!(f, "Integer size is not supported"),
-            Error::ObjectNotSupported(ref v) => write!(f, "Object type 0x{:X} is not supported", v),
-            Error::UnexpectedXmlEof => write!(f, "The XML file ends unexpectedly"),
-            Error::UnexpectedXmlEvent(ref e) => write!(f, "The XML event {:?} is unexpected", e),
-            Error::XmlObjectNotSupported(ref s) => {
+            ErrorKind::InvalidMagicBytes => write!(f, "Magic bytes are incorrect"),
+            ErrorKind::InvalidTrailer => write!(f, "Trailer is invalid"),
+            ErrorKind::VersionNotSupported(Some(ref s)) => write!(f, "Version {} not supported", s),
+            ErrorKind::VersionNotSupported(None) => write!(f, "Version not supported"),
+            ErrorKind::InvalidKeyObject => write!(f, "Key object is not a string"),
+            ErrorKind::InvalidBoolean => write!(f, "Boolean object has an invalid value"),
+            ErrorKind::InvalidIntegerSize => write!(f, "Integer size is not supported"),
+            ErrorKind::ObjectNotSupported(ref v) => {
+                write!(f, "Object type 0x{:X} is not supported", v)
+            }
+            ErrorKind::InvalidObjectReference(ref o) => write!(f, "Object {} does not exist", o),
+            ErrorKind::InvalidObjectOffset(ref o) => write!(f, "Object offset {} is invalid", o),
+            ErrorKind::RecursiveObject(ref o) => write!(f, "Object {} contains itself", o),
+            ErrorKind::MaxDepthExceeded => write!(f, "Collections are nested too deeply"),
+            ErrorKind::TooManyObjects => write!(f, "The property list has too many objects"),
+            ErrorKind::StringTooLong => write!(f, "String is too long"),
+            ErrorKind::DataTooLong => write!(f, "Data is too long"),
+            ErrorKind::CollectionTooLong => write!(f, "Collection has too many elements"),
+            ErrorKind::UnexpectedXmlEof => write!(f, "The XML file ends unexpectedly"),
+            ErrorKind::UnexpectedXmlEvent(ref e) => {
+                write!(f, "The XML event {} is unexpected", e)
+            }
+            ErrorKind::XmlObjectNotSupported(ref s) => {
                 write!(f, "The XML object {:} is not supported", s)
             }
-            Error::XmlError(ref e) => e.fmt(f),
-            Error::IoError(ref e) => e.fmt(f),
-            Error::IntError(ref e) => e.fmt(f),
-            Error::FloatError(ref e) => e.fmt(f),
-            Error::DateError(ref e) => e.fmt(f),
-            Error::Base64Error(ref e) => e.fmt(f),
-            Error::Utf8Error(ref e) => e.fmt(f),
-            Error::Utf16Error(ref e) => e.fmt(f),
+            ErrorKind::InvalidXml => write!(f, "The XML is invalid"),
+            ErrorKind::UnexpectedEof => write!(f, "The property list ends unexpectedly"),
+            ErrorKind::InvalidArchive(ref s) => write!(f, "The keyed archive is invalid: {}", s),
+            ErrorKind::NullNotSupported => write!(f, "Null values are only supported in binary"),
+            ErrorKind::DateOutOfRange => write!(f, "The date is out of range"),
+            ErrorKind::UnexpectedType(ref t) => write!(f, "The value cannot be converted to {}", t),
+            ErrorKind::IntegerOutOfRange(ref t) => write!(f, "The integer does not fit in {}", t),
+            ErrorKind::UnexpectedAsciiEof => write!(f, "The ASCII file ends unexpectedly"),
+            ErrorKind::UnexpectedAsciiCharacter(ref c) => {
+                write!(f, "The ASCII character {:?} is unexpected", c)
+            }
+            ErrorKind::Io => write!(f, "An I/O error occurred"),
+            ErrorKind::InvalidInteger => write!(f, "Integer is invalid"),
+            ErrorKind::InvalidReal => write!(f, "Real number is invalid"),
+            ErrorKind::InvalidDate => write!(f, "Date is invalid"),
+            ErrorKind::InvalidData => write!(f, "Data is not valid base64"),
+            ErrorKind::InvalidUtf8 => write!(f, "String is not valid UTF-8"),
+            ErrorKind::InvalidUtf16 => write!(f, "String is not valid UTF-16"),
+            ErrorKind::Serde(ref s) => write!(f, "{}", s),
         }
     }
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::InvalidMagicBytes => "Magic bytes are incorrect",
-            Error::InvalidTrailer => "Trailer is invalid",
-            Error::VersionNotSupported(ref _s) => "Version not supported",
-            Error::InvalidKeyObject => "Key object is not a string",
-            Error::InvalidBoolean => "Boolean object has an invalid value",
-            Error::InvalidIntegerSize => "Integer size is not supported",
-            Error::ObjectNotSupported(ref _v) => "Object type is not supported",
-            Error::UnexpectedXmlEof => "The XML stream ends unexpectedly",
-            Error::UnexpectedXmlEvent(ref _e) => "The XML event is unexpected",
-            Error::XmlObjectNotSupported(ref _s) => "The XML object is not supported",
-            Error::XmlError(ref e) => e.description(),
-            Error::IoError(ref e) => e.description(),
-            Error::IntError(ref e) => e.description(),
-            Error::FloatError(ref e) => e.description(),
-            Error::DateError(ref e) => e.description(),
-            Error::Base64Error(ref e) => e.description(),
-            Error::Utf8Error(ref e) => e.description(),
-            Error::Utf16Error(ref e) => e.description(),
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.source {
+            Some(ref e) => Some(&**e),
+            None => None,
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        ErrorKind::Serde(msg.to_string()).into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        ErrorKind::Serde(msg.to_string()).into()
+    }
+}
+
 /// The result type returned when parsing a property list
 pub type Result<T> = result::Result<T, Error>;
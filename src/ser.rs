@@ -0,0 +1,467 @@
+//! Serialization of Rust types to property lists using serde.
+//!
+//! # Examples
+//!
+//! ```rust
+//! extern crate plist;
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! #[derive(Serialize)]
+//! struct Info {
+//!     #[serde(rename = "CFBundleIdentifier")]
+//!     identifier: String,
+//! }
+//!
+//! fn main() {
+//!     let info = Info { identifier: "com.example.app".to_string() };
+//!
+//!     let mut buf = Vec::new();
+//!     plist::ser::to_binary_writer(&mut buf, &info).unwrap();
+//!     assert_eq!(&buf[..8], b"bplist00");
+//! }
+//! ```
+//!
+//! `None` and `()` are serialized as `Plist::Null`, which only binary property
+//! lists can store. Struct fields that are `None` are left out, so optional
+//! fields are simply omitted, whereas `()` fields are kept as null.
+
+use std::io::Write;
+use serde::ser::{self, Serialize};
+
+use date::{Date, DATE_NEWTYPE_STRUCT_NAME};
+use plist::{Plist, Dictionary};
+use result::{Result, Error, ErrorKind};
+
+/// A serde serializer that converts Rust values into a `Plist`.
+#[derive(Debug)]
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Plist;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeDict;
+    type SerializeStruct = SerializeDict;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Plist> {
+        Ok(Plist::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Plist> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Plist> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Plist> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Plist> {
+        Ok(Plist::Integer(v.into()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Plist> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Plist> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Plist> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Plist> {
+        Ok(Plist::Integer(v.into()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Plist> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Plist> {
+        Ok(Plist::Real(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Plist> {
+        Ok(Plist::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Plist> {
+        Ok(Plist::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Plist> {
+        Ok(Plist::Data(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Plist> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Plist> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Plist> {
+        Ok(Plist::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Plist> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self,
+                              _name: &'static str,
+                              _variant_index: u32,
+                              variant: &'static str)
+                              -> Result<Plist> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self,
+                                                       name: &'static str,
+                                                       value: &T)
+                                                       -> Result<Plist> {
+        match try!(value.serialize(self)) {
+            Plist::String(ref s) if name == DATE_NEWTYPE_STRUCT_NAME => {
+                Ok(Plist::DateTime(try!(Date::from_rfc3339(s))))
+            }
+            plist => Ok(plist),
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                        _name: &'static str,
+                                                        _variant_index: u32,
+                                                        variant: &'static str,
+                                                        value: &T)
+                                                        -> Result<Plist> {
+        let mut dict = Dictionary::default();
+        dict.insert(variant.to_string(), try!(value.serialize(self)));
+        Ok(Plist::Dict(dict))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
+        Ok(SerializeArray { array: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self,
+                               _name: &'static str,
+                               _variant_index: u32,
+                               variant: &'static str,
+                               len: usize)
+                               -> Result<SerializeTupleVariant> {
+        Ok(SerializeTupleVariant {
+            variant: variant,
+            array: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeDict> {
+        Ok(SerializeDict {
+            dict: Dictionary::default(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeDict> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self,
+                                _name: &'static str,
+                                _variant_index: u32,
+                                variant: &'static str,
+                                _len: usize)
+                                -> Result<SerializeStructVariant> {
+        Ok(SerializeStructVariant {
+            variant: variant,
+            dict: Dictionary::default(),
+        })
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct SerializeArray {
+    array: Vec<Plist>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.array.push(try!(value.serialize(Serializer)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Plist> {
+        Ok(Plist::Array(self.array))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Plist> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Plist> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    array: Vec<Plist>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.array.push(try!(value.serialize(Serializer)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Plist> {
+        let mut dict = Dictionary::default();
+        dict.insert(self.variant.to_string(), Plist::Array(self.array));
+        Ok(Plist::Dict(dict))
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct SerializeDict {
+    dict: Dictionary,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeDict {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        match try!(key.serialize(Serializer)) {
+            Plist::String(s) => {
+                self.key = Some(s);
+                Ok(())
+            }
+            _ => Err(ErrorKind::InvalidKeyObject.into()),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = try!(self.key.take().ok_or_else(|| {
+            ErrorKind::Serde("A map value was serialized before its key".to_string())
+        }));
+        self.dict.insert(key, try!(value.serialize(Serializer)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Plist> {
+        Ok(Plist::Dict(self.dict))
+    }
+}
+
+impl ser::SerializeStruct for SerializeDict {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self,
+                                              key: &'static str,
+                                              value: &T)
+                                              -> Result<()> {
+        insert_field(&mut self.dict, key, value)
+    }
+
+    fn end(self) -> Result<Plist> {
+        Ok(Plist::Dict(self.dict))
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    dict: Dictionary,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Plist;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self,
+                                              key: &'static str,
+                                              value: &T)
+                                              -> Result<()> {
+        insert_field(&mut self.dict, key, value)
+    }
+
+    fn end(self) -> Result<Plist> {
+        let mut dict = Dictionary::default();
+        dict.insert(self.variant.to_string(), Plist::Dict(self.dict));
+        Ok(Plist::Dict(dict))
+    }
+}
+
+macro_rules! forward_to_serializer {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<$ok> {
+                Serializer.$method($($arg),*)
+            }
+        )*
+    }
+}
+
+// Serializes a struct field as `Serializer` does, recording whether it is
+// `None` rather than another value that serializes to null
+struct FieldSerializer<'a> {
+    none: &'a mut bool,
+}
+
+impl<'a> ser::Serializer for FieldSerializer<'a> {
+    type Ok = Plist;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeDict;
+    type SerializeStruct = SerializeDict;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_none(self) -> Result<Plist> {
+        *self.none = true;
+        Serializer.serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Plist> {
+        Serializer.serialize_some(value)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self,
+                                                       name: &'static str,
+                                                       value: &T)
+                                                       -> Result<Plist> {
+        Serializer.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                        name: &'static str,
+                                                        variant_index: u32,
+                                                        variant: &'static str,
+                                                        value: &T)
+                                                        -> Result<Plist> {
+        Serializer.serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    forward_to_serializer! {
+        serialize_bool(v: bool) -> Plist;
+        serialize_i8(v: i8) -> Plist;
+        serialize_i16(v: i16) -> Plist;
+        serialize_i32(v: i32) -> Plist;
+        serialize_i64(v: i64) -> Plist;
+        serialize_u8(v: u8) -> Plist;
+        serialize_u16(v: u16) -> Plist;
+        serialize_u32(v: u32) -> Plist;
+        serialize_u64(v: u64) -> Plist;
+        serialize_f32(v: f32) -> Plist;
+        serialize_f64(v: f64) -> Plist;
+        serialize_char(v: char) -> Plist;
+        serialize_str(v: &str) -> Plist;
+        serialize_bytes(v: &[u8]) -> Plist;
+        serialize_unit() -> Plist;
+        serialize_unit_struct(name: &'static str) -> Plist;
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str) -> Plist;
+        serialize_seq(len: Option<usize>) -> SerializeArray;
+        serialize_tuple(len: usize) -> SerializeArray;
+        serialize_tuple_struct(name: &'static str, len: usize) -> SerializeArray;
+        serialize_tuple_variant(name: &'static str,
+                                index: u32,
+                                variant: &'static str,
+                                len: usize)
+                                -> SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> SerializeDict;
+        serialize_struct(name: &'static str, len: usize) -> SerializeDict;
+        serialize_struct_variant(name: &'static str,
+                                 index: u32,
+                                 variant: &'static str,
+                                 len: usize)
+                                 -> SerializeStructVariant;
+    }
+}
+
+fn insert_field<T: ?Sized + Serialize>(dict: &mut Dictionary,
+                                       key: &'static str,
+                                       value: &T)
+                                       -> Result<()> {
+    let mut none = false;
+    let value = try!(value.serialize(FieldSerializer { none: &mut none }));
+    if !none {
+        dict.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Serializes a value of type `T` into a property list value.
+pub fn to_plist<T: ?Sized + Serialize>(value: &T) -> Result<Plist> {
+    value.serialize(Serializer)
+}
+
+/// Serializes a value of type `T` as a binary property list to a writer.
+pub fn to_binary_writer<W: Write, T: ?Sized + Serialize>(output: &mut W, value: &T) -> Result<()> {
+    try!(to_plist(value)).to_binary_writer(output)
+}
+
+/// Serializes a value of type `T` as an XML property list to a writer.
+pub fn to_xml_writer<W: Write, T: ?Sized + Serialize>(output: &mut W, value: &T) -> Result<()> {
+    try!(to_plist(value)).to_xml_writer(output)
+}
+
+/// Serializes a value of type `T` as an ASCII (OpenStep) property list to a
+/// writer.
+pub fn to_ascii_writer<W: Write, T: ?Sized + Serialize>(output: &mut W, value: &T) -> Result<()> {
+    try!(to_plist(value)).to_ascii_writer(output)
+}
@@ -0,0 +1,132 @@
+use std::io::Write;
+
+use date::{Date, naive_date};
+use plist::Plist;
+use result::{Result, ErrorKind};
+use super::{WriterConfig, dict_entries};
+
+// Xcode writes this marker to declare the encoding of project files
+const HEADER: &'static str = "// !$*UTF8*$!\n";
+
+#[inline]
+fn ascii_indent<W: Write>(output: &mut W, depth: usize) -> Result<()> {
+    for _ in 0..depth {
+        try!(output.write_all(b"\t"));
+    }
+    Ok(())
+}
+
+#[inline]
+fn is_unquoted_char(c: char) -> bool {
+    match c {
+        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '$' | '/' | ':' | '.' => true,
+        _ => false,
+    }
+}
+
+fn ascii_string(string: &str) -> String {
+    // Xcode also quotes strings that could be mistaken for comments or
+    // placeholders
+    if !string.is_empty() && string.chars().all(is_unquoted_char) && !string.contains("//") &&
+       !string.contains("___") {
+        return string.to_string();
+    }
+
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\x07' => quoted.push_str("\\a"),
+            '\x08' => quoted.push_str("\\b"),
+            '\x0C' => quoted.push_str("\\f"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\x0B' => quoted.push_str("\\v"),
+            c if (c as u32) < 0x20 || c == '\x7F' => {
+                quoted.push_str(&format!("\\U{:04x}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn ascii_data(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2 + data.len() / 4 + 2);
+    hex.push('<');
+    for (i, b) in data.iter().enumerate() {
+        if i > 0 && i % 4 == 0 {
+            hex.push(' ');
+        }
+        hex.push_str(&format!("{:02x}", b));
+    }
+    hex.push('>');
+    hex
+}
+
+fn ascii_date(date: &Date) -> Result<String> {
+    // Like NSDate's description, the date is only precise to the second
+    let date = try!(naive_date(date).ok_or(ErrorKind::DateOutOfRange));
+    Ok(date.format("%Y-%m-%d %H:%M:%S +0000").to_string())
+}
+
+fn ascii_object<W: Write>(output: &mut W,
+                          depth: usize,
+                          plist: &Plist,
+                          config: &WriterConfig)
+                          -> Result<()> {
+    match *plist {
+        Plist::Array(ref array) => {
+            try!(output.write_all(b"(\n"));
+            for value in array {
+                try!(ascii_indent(output, depth + 1));
+                try!(ascii_object(output, depth + 1, value, config));
+                try!(output.write_all(b",\n"));
+            }
+            try!(ascii_indent(output, depth));
+            try!(output.write_all(b")"));
+        }
+        Plist::Dict(ref dict) => {
+            try!(output.write_all(b"{\n"));
+            for (key, value) in dict_entries(dict, config) {
+                try!(ascii_indent(output, depth + 1));
+                try!(write!(output, "{} = ", ascii_string(key)));
+                try!(ascii_object(output, depth + 1, value, config));
+                try!(output.write_all(b";\n"));
+            }
+            try!(ascii_indent(output, depth));
+            try!(output.write_all(b"}"));
+        }
+        Plist::Boolean(b) => try!(output.write_all(if b { b"YES" } else { b"NO" })),
+        Plist::Data(ref d) => try!(output.write_all(ascii_data(d).as_bytes())),
+        Plist::DateTime(ref d) => {
+            try!(output.write_all(ascii_string(&try!(ascii_date(d))).as_bytes()))
+        }
+        Plist::Real(r) => try!(output.write_all(ascii_string(&r.to_string()).as_bytes())),
+        Plist::Integer(i) => try!(output.write_all(ascii_string(&i.to_string()).as_bytes())),
+        Plist::String(ref s) => try!(output.write_all(ascii_string(s).as_bytes())),
+        Plist::Null => return Err(ErrorKind::NullNotSupported.into()),
+        Plist::Uid(u) => {
+            try!(output.write_all(b"{\n"));
+            try!(ascii_indent(output, depth + 1));
+            try!(write!(output, "CF$UID = {};\n", u));
+            try!(ascii_indent(output, depth));
+            try!(output.write_all(b"}"));
+        }
+    }
+    Ok(())
+}
+
+pub fn to_ascii_writer<W: Write>(output: &mut W,
+                                 plist: &Plist,
+                                 config: &WriterConfig)
+                                 -> Result<()> {
+    try!(output.write_all(HEADER.as_bytes()));
+    try!(ascii_object(output, 0, plist, config));
+    try!(output.write_all(b"\n"));
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::io::Write;
+use fnv::FnvHasher;
+
+use integer::Integer;
+use plist::Plist;
+use result::Result;
+use super::{WriterConfig, dict_entries};
+
+enum Object<'a> {
+    Value(&'a Plist),
+    String(&'a str),
+    Array(Vec<u64>),
+    Dict(Vec<u64>, Vec<u64>),
+}
+
+type StringTable<'a> = HashMap<&'a str, u64, BuildHasherDefault<FnvHasher>>;
+
+#[inline]
+fn int_size(value: u64) -> u8 {
+    if value <= 0xFF {
+        1
+    } else if value <= 0xFFFF {
+        2
+    } else if value <= 0xFFFF_FFFF {
+        4
+    } else {
+        8
+    }
+}
+
+#[inline]
+fn write_sized<W: Write>(output: &mut W, value: u64, size: u8) -> Result<()> {
+    let mut buf = [0; 8];
+    for i in 0..size as usize {
+        buf[i] = (value >> (8 * (size as usize - i - 1))) as u8;
+    }
+    try!(output.write_all(&buf[..size as usize]));
+    Ok(())
+}
+
+#[inline]
+fn write_int<W: Write>(output: &mut W, value: i64) -> Result<()> {
+    // Negative integers are always stored as eight bytes
+    let size = if value < 0 { 8 } else { int_size(value as u64) };
+    try!(output.write_all(&[0x10 | size.trailing_zeros() as u8]));
+    write_sized(output, value as u64, size)
+}
+
+fn write_integer<W: Write>(output: &mut W, value: Integer) -> Result<()> {
+    match (value.as_signed(), value.as_unsigned()) {
+        (Some(i), _) => write_int(output, i),
+        (None, Some(u)) => {
+            // Values above i64::MAX are stored as sixteen byte integers
+            try!(output.write_all(&[0x14]));
+            try!(write_sized(output, 0, 8));
+            write_sized(output, u, 8)
+        }
+        (None, None) => unreachable!(),
+    }
+}
+
+#[inline]
+fn write_marker<W: Write>(output: &mut W, marker: u8, len: usize) -> Result<()> {
+    if len < 0xF {
+        try!(output.write_all(&[marker | len as u8]));
+        Ok(())
+    } else {
+        try!(output.write_all(&[marker | 0xF]));
+        write_int(output, len as i64)
+    }
+}
+
+#[inline]
+fn write_refs<W: Write>(output: &mut W, refs: &[u64], ref_size: u8) -> Result<()> {
+    for r in refs {
+        try!(write_sized(output, *r, ref_size));
+    }
+    Ok(())
+}
+
+fn write_string<W: Write>(output: &mut W, string: &str) -> Result<()> {
+    if string.is_ascii() {
+        try!(write_marker(output, 0x50, string.len()));
+        try!(output.write_all(string.as_bytes()));
+    } else {
+        let points: Vec<u16> = string.encode_utf16().collect();
+        try!(write_marker(output, 0x60, points.len()));
+        for p in points {
+            try!(write_sized(output, p as u64, 2));
+        }
+    }
+    Ok(())
+}
+
+fn write_object<W: Write>(output: &mut W, object: &Object, ref_size: u8) -> Result<()> {
+    match *object {
+        Object::String(s) => write_string(output, s),
+        Object::Array(ref values) => {
+            try!(write_marker(output, 0xA0, values.len()));
+            write_refs(output, values, ref_size)
+        }
+        Object::Dict(ref keys, ref values) => {
+            try!(write_marker(output, 0xD0, keys.len()));
+            try!(write_refs(output, keys, ref_size));
+            write_refs(output, values, ref_size)
+        }
+        Object::Value(&Plist::Boolean(b)) => {
+            try!(output.write_all(&[if b { 0x09 } else { 0x08 }]));
+            Ok(())
+        }
+        Object::Value(&Plist::Integer(i)) => write_integer(output, i),
+        Object::Value(&Plist::Real(r)) => {
+            try!(output.write_all(&[0x23]));
+            write_sized(output, r.to_bits(), 8)
+        }
+        Object::Value(&Plist::DateTime(ref d)) => {
+            try!(output.write_all(&[0x33]));
+            write_sized(output, d.absolute_time().to_bits(), 8)
+        }
+        Object::Value(&Plist::Null) => {
+            try!(output.write_all(&[0x00]));
+            Ok(())
+        }
+        Object::Value(&Plist::Uid(u)) => {
+            let size = int_size(u);
+            try!(output.write_all(&[0x80 | (size - 1)]));
+            write_sized(output, u, size)
+        }
+        Object::Value(&Plist::Data(ref d)) => {
+            try!(write_marker(output, 0x40, d.len()));
+            try!(output.write_all(d));
+            Ok(())
+        }
+        Object::Value(_) => unreachable!(),
+    }
+}
+
+fn string<'a>(string: &'a str,
+              objects: &mut Vec<Object<'a>>,
+              strings: &mut StringTable<'a>)
+              -> u64 {
+    if let Some(index) = strings.get(string) {
+        return *index;
+    }
+
+    let index = objects.len() as u64;
+    objects.push(Object::String(string));
+    strings.insert(string, index);
+    index
+}
+
+fn flatten<'a>(plist: &'a Plist,
+               objects: &mut Vec<Object<'a>>,
+               strings: &mut StringTable<'a>,
+               config: &WriterConfig)
+               -> u64 {
+    let index = objects.len() as u64;
+    match *plist {
+        Plist::String(ref s) => return string(s, objects, strings),
+        Plist::Array(ref array) => {
+            objects.push(Object::Array(Vec::new()));
+            let values = array.iter().map(|v| flatten(v, objects, strings, config)).collect();
+            objects[index as usize] = Object::Array(values);
+        }
+        Plist::Dict(ref dict) => {
+            objects.push(Object::Dict(Vec::new(), Vec::new()));
+            let entries = dict_entries(dict, config);
+            let keys = entries.iter().map(|e| string(e.0, objects, strings)).collect();
+            let values = entries.iter().map(|e| flatten(e.1, objects, strings, config)).collect();
+            objects[index as usize] = Object::Dict(keys, values);
+        }
+        _ => objects.push(Object::Value(plist)),
+    }
+    index
+}
+
+pub fn to_binary_writer<W: Write>(output: &mut W,
+                                  plist: &Plist,
+                                  config: &WriterConfig)
+                                  -> Result<()> {
+    let mut objects = Vec::new();
+    let mut strings = StringTable::default();
+    let root = flatten(plist, &mut objects, &mut strings, config);
+
+    let ref_size = int_size(objects.len() as u64 - 1);
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(b"bplist00");
+
+    let mut offsets = Vec::with_capacity(objects.len());
+    for object in &objects {
+        offsets.push(buf.len() as u64);
+        try!(write_object(&mut buf, object, ref_size));
+    }
+
+    let table_offset = buf.len() as u64;
+    let offset_size = int_size(offsets[offsets.len() - 1]);
+    for offset in offsets {
+        try!(write_sized(&mut buf, offset, offset_size));
+    }
+
+    buf.extend_from_slice(&[0; 6]);
+    buf.extend_from_slice(&[offset_size, ref_size]);
+    try!(write_sized(&mut buf, objects.len() as u64, 8));
+    try!(write_sized(&mut buf, root, 8));
+    try!(write_sized(&mut buf, table_offset, 8));
+
+    try!(output.write_all(&buf));
+    Ok(())
+}
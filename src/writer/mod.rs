@@ -0,0 +1,32 @@
+use plist::{Plist, Dictionary};
+
+pub mod ascii;
+pub mod binary;
+pub mod xml;
+
+/// Options applied while writing a property list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriterConfig {
+    /// Whether dictionary keys are written in sorted order, the way `plutil`
+    /// and `CFPropertyList` write them, rather than in insertion order.
+    ///
+    /// Sorting the keys makes XML output identical to `plutil -convert xml1`.
+    pub sort_keys: bool,
+}
+
+impl Default for WriterConfig {
+    fn default() -> WriterConfig {
+        WriterConfig { sort_keys: false }
+    }
+}
+
+/// Returns the entries of a dictionary in the order `config` writes them.
+pub fn dict_entries<'a>(dict: &'a Dictionary,
+                        config: &WriterConfig)
+                        -> Vec<(&'a String, &'a Plist)> {
+    let mut entries: Vec<_> = dict.iter().collect();
+    if config.sort_keys {
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+    }
+    entries
+}
@@ -0,0 +1,152 @@
+use std::io::Write;
+use rustc_serialize::base64::{self, ToBase64};
+
+use plist::Plist;
+use result::{Result, ErrorKind};
+use super::{WriterConfig, dict_entries};
+
+const HEADER: &'static str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \
+                              \"-//Apple//DTD PLIST 1.0//EN\" \
+                              \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist \
+                              version=\"1.0\">\n";
+
+// CFPropertyList wraps base64 at 76 columns, counting each tab of indentation
+// as 8 columns, and never indents data by more than 8 tabs.
+const MAX_LINE_LENGTH: usize = 76;
+const MAX_DATA_INDENT: usize = 8;
+
+#[inline]
+fn xml_indent<W: Write>(output: &mut W, depth: usize) -> Result<()> {
+    for _ in 0..depth {
+        try!(output.write_all(b"\t"));
+    }
+    Ok(())
+}
+
+#[inline]
+fn xml_escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn xml_element<W: Write>(output: &mut W, depth: usize, name: &str, content: &str) -> Result<()> {
+    try!(xml_indent(output, depth));
+    try!(write!(output, "<{}>{}</{}>\n", name, content, name));
+    Ok(())
+}
+
+fn xml_real(real: f64) -> String {
+    if real.is_nan() {
+        return "nan".to_string();
+    } else if real.is_infinite() {
+        return if real > 0.0 { "+infinity" } else { "-infinity" }.to_string();
+    }
+
+    // Match the "%g" format used by CFPropertyList, which switches to
+    // scientific notation for very large and very small exponents
+    let exp = if real == 0.0 { 0 } else { real.abs().log10().floor() as i32 };
+    if exp < -4 || exp >= 17 {
+        let formatted = format!("{:e}", real);
+        let mut parts = formatted.splitn(2, 'e');
+        let mantissa = parts.next().unwrap_or("");
+        let exp: i32 = parts.next().and_then(|e| e.parse().ok()).unwrap_or(0);
+        format!("{}e{}{:02}", mantissa, if exp < 0 { '-' } else { '+' }, exp.abs())
+    } else {
+        format!("{}", real)
+    }
+}
+
+fn xml_data<W: Write>(output: &mut W, depth: usize, data: &[u8]) -> Result<()> {
+    let indent = if depth > MAX_DATA_INDENT { MAX_DATA_INDENT } else { depth };
+    let line_length = MAX_LINE_LENGTH - 8 * indent;
+    let encoded = data.to_base64(base64::STANDARD);
+
+    try!(xml_indent(output, depth));
+    try!(output.write_all(b"<data>\n"));
+    for line in encoded.as_bytes().chunks(line_length) {
+        try!(xml_indent(output, indent));
+        try!(output.write_all(line));
+        try!(output.write_all(b"\n"));
+    }
+    try!(xml_indent(output, depth));
+    try!(output.write_all(b"</data>\n"));
+    Ok(())
+}
+
+fn xml_object<W: Write>(output: &mut W,
+                        depth: usize,
+                        plist: &Plist,
+                        config: &WriterConfig)
+                        -> Result<()> {
+    match *plist {
+        Plist::Array(ref array) => {
+            try!(xml_indent(output, depth));
+            if array.is_empty() {
+                try!(output.write_all(b"<array/>\n"));
+                return Ok(());
+            }
+
+            try!(output.write_all(b"<array>\n"));
+            for value in array {
+                try!(xml_object(output, depth + 1, value, config));
+            }
+            try!(xml_indent(output, depth));
+            try!(output.write_all(b"</array>\n"));
+            Ok(())
+        }
+        Plist::Dict(ref dict) => {
+            try!(xml_indent(output, depth));
+            if dict.is_empty() {
+                try!(output.write_all(b"<dict/>\n"));
+                return Ok(());
+            }
+
+            try!(output.write_all(b"<dict>\n"));
+            for (key, value) in dict_entries(dict, config) {
+                try!(xml_element(output, depth + 1, "key", &xml_escape(key)));
+                try!(xml_object(output, depth + 1, value, config));
+            }
+            try!(xml_indent(output, depth));
+            try!(output.write_all(b"</dict>\n"));
+            Ok(())
+        }
+        Plist::Boolean(b) => {
+            try!(xml_indent(output, depth));
+            try!(output.write_all(if b { b"<true/>\n" } else { b"<false/>\n" }));
+            Ok(())
+        }
+        Plist::Data(ref d) => xml_data(output, depth, d),
+        Plist::DateTime(ref d) => xml_element(output, depth, "date", &try!(d.to_rfc3339())),
+        Plist::Real(r) => xml_element(output, depth, "real", &xml_real(r)),
+        Plist::Integer(i) => xml_element(output, depth, "integer", &i.to_string()),
+        Plist::String(ref s) => xml_element(output, depth, "string", &xml_escape(s)),
+        Plist::Null => Err(ErrorKind::NullNotSupported.into()),
+        Plist::Uid(u) => {
+            try!(xml_indent(output, depth));
+            try!(output.write_all(b"<dict>\n"));
+            try!(xml_element(output, depth + 1, "key", "CF$UID"));
+            try!(xml_element(output, depth + 1, "integer", &u.to_string()));
+            try!(xml_indent(output, depth));
+            try!(output.write_all(b"</dict>\n"));
+            Ok(())
+        }
+    }
+}
+
+pub fn to_xml_writer<W: Write>(output: &mut W,
+                               plist: &Plist,
+                               config: &WriterConfig)
+                               -> Result<()> {
+    try!(output.write_all(HEADER.as_bytes()));
+    try!(xml_object(output, 0, plist, config));
+    try!(output.write_all(b"</plist>\n"));
+    Ok(())
+}
@@ -0,0 +1,169 @@
+extern crate plist;
+
+use std::fs::File;
+use std::io::{Cursor, Read};
+use plist::{PathSegment, Plist, Position, WriterConfig};
+
+fn string(s: &str) -> Plist {
+    Plist::String(s.to_string())
+}
+
+#[test]
+fn test_ascii_types() {
+    let mut f = File::open("tests/types-ascii.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let dict = match plist {
+        Plist::Dict(dict) => dict,
+        _ => panic!("Root object is not a dictionary"),
+    };
+
+    assert_eq!(dict["String"], string("Hello"));
+    assert_eq!(dict["Quoted String"], string("Hello, World"));
+    assert_eq!(dict["Single Quoted"], string("It's"));
+    assert_eq!(dict["Escapes"], string("\t\"\\\n\u{e9}👿A"));
+    assert_eq!(dict["Path"], string("/usr/bin/env"));
+    assert_eq!(dict["Data"], Plist::Data(vec![0, 1, 2, 3, 4, 5, 6]));
+    assert_eq!(dict["Array"],
+               Plist::Array(vec![string("one"), string("two"), Plist::Array(vec![])]));
+    assert_eq!(dict["👿"], string("UTF16String"));
+
+    match dict["Dict"] {
+        Plist::Dict(ref d) => assert_eq!(d["Empty"], Plist::Dict(Default::default())),
+        _ => panic!("Dict is not a dictionary"),
+    }
+}
+
+#[test]
+fn test_ascii_strings_file() {
+    let strings = "/* Greeting */\n\"Hello\" = \"Bonjour\";\nGoodbye;\n";
+    let plist = Plist::from_reader(&mut Cursor::new(strings)).unwrap();
+
+    match plist {
+        Plist::Dict(dict) => {
+            assert_eq!(dict.len(), 2);
+            assert_eq!(dict["Hello"], string("Bonjour"));
+            assert_eq!(dict["Goodbye"], string("Goodbye"));
+        }
+        _ => panic!("Strings file is not a dictionary"),
+    }
+}
+
+#[test]
+fn test_ascii_utf16_strings_file() {
+    let mut le = Vec::new();
+    File::open("tests/utf16.strings").unwrap().read_to_end(&mut le).unwrap();
+    let be: Vec<u8> = le.chunks(2).flat_map(|x| vec![x[1], x[0]]).collect();
+
+    for bytes in vec![le, be] {
+        let plist = Plist::from_reader(&mut Cursor::new(&bytes)).unwrap();
+        match plist {
+            Plist::Dict(dict) => {
+                assert_eq!(dict.len(), 2);
+                assert_eq!(dict["Hello"], string("Bonjour 👋"));
+                assert_eq!(dict["Goodbye"], string("Goodbye"));
+            }
+            _ => panic!("Strings file is not a dictionary"),
+        }
+        assert!(Plist::from_ascii_reader(&mut Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+    }
+}
+
+#[test]
+fn test_ascii_invalid() {
+    assert!(Plist::from_ascii_reader(&mut Cursor::new("{ a = b }")).is_err());
+    assert!(Plist::from_ascii_reader(&mut Cursor::new("(a, b")).is_err());
+    assert!(Plist::from_ascii_reader(&mut Cursor::new("<0g>")).is_err());
+    assert!(Plist::from_ascii_reader(&mut Cursor::new("a b")).is_err());
+}
+
+#[test]
+fn test_ascii_roundtrip() {
+    let mut f = File::open("tests/types-ascii.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let mut buf = Vec::new();
+    plist.to_ascii_writer(&mut buf).unwrap();
+    let decoded = Plist::from_ascii_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(plist, decoded);
+}
+
+#[test]
+fn test_ascii_uid() {
+    let plist = Plist::Array(vec![Plist::Uid(3)]);
+
+    let mut buf = Vec::new();
+    plist.to_ascii_writer(&mut buf).unwrap();
+    let output = String::from_utf8(buf).unwrap();
+    assert!(output.contains("\t{\n\t\tCF$UID = 3;\n\t}"));
+
+    let decoded = Plist::from_ascii_reader(&mut Cursor::new(output.as_bytes())).unwrap();
+    assert_eq!(decoded, plist);
+
+    // Only a dictionary with nothing but a CF$UID integer is a UID
+    let dict = Plist::from_ascii_reader(&mut Cursor::new("{ CF$UID = a; }")).unwrap();
+    assert_eq!(dict["CF$UID"], string("a"));
+}
+
+#[test]
+fn test_ascii_xcode_output() {
+    let mut f = File::open("tests/types-ascii.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    // Xcode sorts keys
+    let mut buf = Vec::new();
+    plist.to_ascii_writer_with_config(&mut buf, &WriterConfig { sort_keys: true }).unwrap();
+    let expected = "// !$*UTF8*$!
+{
+\tArray = (
+\t\tone,
+\t\ttwo,
+\t\t(
+\t\t),
+\t);
+\tData = <00010203 040506>;
+\tDict = {
+\t\tEmpty = {
+\t\t};
+\t};
+\tEscapes = \"\\t\\\"\\\\\\n\u{e9}👿A\";
+\tPath = /usr/bin/env;
+\t\"Quoted String\" = \"Hello, World\";
+\t\"Single Quoted\" = \"It's\";
+\tString = Hello;
+\t\"👿\" = UTF16String;
+}
+";
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+    // By default, the keys keep the order of the source document
+    let mut buf = Vec::new();
+    plist.to_ascii_writer(&mut buf).unwrap();
+    let output = String::from_utf8(buf).unwrap();
+    assert!(output.starts_with("// !$*UTF8*$!\n{\n\tString = Hello;\n"));
+}
+
+#[test]
+fn test_ascii_error_position() {
+    let ascii = "{\n\tOne = 1;\n\tTwo = (2, 3];\n}";
+    let error = Plist::from_ascii_reader(&mut Cursor::new(ascii)).unwrap_err();
+    assert_eq!(error.position(),
+               Some(Position::Text {
+                   line: 3,
+                   column: 13,
+               }));
+}
+
+#[test]
+fn test_ascii_error_path() {
+    let ascii = "{ Types = ({ Name = A; }, { Name = B; Extensions = (a, b, <0g>); }); }";
+    let error = Plist::from_ascii_reader(&mut Cursor::new(ascii)).unwrap_err();
+    assert_eq!(error.path(),
+               [PathSegment::Key("Types".to_string()),
+                PathSegment::Index(1),
+                PathSegment::Key("Extensions".to_string()),
+                PathSegment::Index(2)]);
+    assert_eq!(error.to_string(),
+               "Types[1].Extensions[2]: The ASCII character 'g' is unexpected at line 1, \
+                column 61");
+}
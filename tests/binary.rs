@@ -0,0 +1,342 @@
+extern crate plist;
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use plist::{BinaryDocument, BinaryEventReader, Error, ErrorKind, Integer, NodeKind, Plist,
+            PathSegment, PlistRef, Position, ReaderConfig};
+
+fn roundtrip(path: &str) {
+    let mut f = File::open(path).unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    let decoded = Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(plist, decoded);
+}
+
+#[test]
+fn test_binary_roundtrip() {
+    roundtrip("tests/types-binary.plist");
+    roundtrip("benches/large-input-binary.plist");
+}
+
+#[test]
+fn test_binary_trailer() {
+    let mut f = File::open("tests/types-xml.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    assert_eq!(&buf[..8], b"bplist00");
+
+    // Duplicate strings are written once, and 16 objects fit in one byte
+    // references and offsets
+    let trailer = &buf[buf.len() - 32..];
+    assert_eq!(&trailer[6..8], &[1, 1]);
+    assert_eq!(&trailer[8..16], &[0, 0, 0, 0, 0, 0, 0, 16]);
+    assert_eq!(&trailer[16..24], &[0; 8]);
+}
+
+fn trailer(buf: &mut Vec<u8>, obj_count: u8, root: u8, table_offset: u8) {
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, obj_count]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, root]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, table_offset]);
+}
+
+#[test]
+fn test_binary_recursive_object() {
+    // An array whose only element is itself
+    let mut buf = b"bplist00\xA1\x00\x08".to_vec();
+    trailer(&mut buf, 1, 0, 10);
+
+    match Plist::from_binary_reader(&mut Cursor::new(buf)).map_err(Error::into_kind) {
+        Err(ErrorKind::RecursiveObject(0)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_binary_max_depth() {
+    let mut plist = Plist::Array(Vec::new());
+    for _ in 0..600 {
+        plist = Plist::Array(vec![plist]);
+    }
+
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+
+    match Plist::from_binary_reader(&mut Cursor::new(&buf)).map_err(Error::into_kind) {
+        Err(ErrorKind::MaxDepthExceeded) => (),
+        r => panic!("Unexpected result {:?}", r.map(|_| ())),
+    }
+
+    let events = BinaryEventReader::with_max_depth(Cursor::new(&buf), 601);
+    assert_eq!(events.map(|e| e.unwrap()).count(), 1202);
+}
+
+#[test]
+fn test_binary_invalid_references() {
+    let decode = |buf: Vec<u8>| Plist::from_binary_reader(&mut Cursor::new(buf));
+
+    // An array that references a missing object
+    let mut buf = b"bplist00\xA1\x05\x08".to_vec();
+    trailer(&mut buf, 1, 0, 10);
+    match decode(buf).map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidObjectReference(5)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // A root object that is out of range
+    let mut buf = b"bplist00\x09\x08".to_vec();
+    trailer(&mut buf, 1, 3, 9);
+    match decode(buf).map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidObjectReference(3)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // An object offset that points into the trailer
+    let mut buf = b"bplist00\x09\x30".to_vec();
+    trailer(&mut buf, 1, 0, 9);
+    match decode(buf).map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidObjectOffset(48)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // An offset table that extends into the trailer
+    let mut buf = b"bplist00\x09\x08".to_vec();
+    trailer(&mut buf, 8, 0, 9);
+    match decode(buf).map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidTrailer) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_binary_truncated() {
+    let mut buf = Vec::new();
+    File::open("tests/types-binary.plist").unwrap().read_to_end(&mut buf).unwrap();
+
+    for len in 0..buf.len() {
+        let mut cursor = Cursor::new(&buf[..len]);
+        assert!(Plist::from_binary_reader(&mut cursor).is_err());
+    }
+}
+
+#[test]
+fn test_binary_uid() {
+    // A UID stored in two bytes
+    let mut buf = b"bplist00\x81\x01\x2C\x08".to_vec();
+    trailer(&mut buf, 1, 0, 11);
+    let plist = Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(plist, Plist::Uid(300));
+
+    let plist = Plist::Array(vec![Plist::Uid(0), Plist::Uid(300), Plist::Uid(1 << 40)]);
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    assert_eq!(Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap(), plist);
+}
+
+#[test]
+fn test_binary_sets() {
+    // An ordered set containing a set, which contains true
+    let mut buf = b"bplist00\xB1\x01\xC1\x02\x09\x08\x0A\x0C".to_vec();
+    trailer(&mut buf, 3, 0, 13);
+
+    let plist = Plist::from_binary_reader(&mut Cursor::new(&buf)).unwrap();
+    let set = Plist::Array(vec![Plist::Boolean(true)]);
+    assert_eq!(plist, Plist::Array(vec![set]));
+
+    let config = ReaderConfig { sets_as_arrays: false, ..ReaderConfig::default() };
+    let result = Plist::from_binary_reader_with_config(&mut Cursor::new(&buf), &config);
+    match result.map_err(Error::into_kind) {
+        Err(ErrorKind::ObjectNotSupported(0xB)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_binary_null() {
+    // An array containing null and fill objects
+    let mut buf = b"bplist00\xA2\x01\x02\x00\x0F\x08\x0B\x0C".to_vec();
+    trailer(&mut buf, 3, 0, 13);
+
+    let plist = Plist::from_binary_reader(&mut Cursor::new(&buf)).unwrap();
+    assert_eq!(plist, Plist::Array(vec![Plist::Null, Plist::Null]));
+
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    assert_eq!(Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap(), plist);
+    match plist.to_xml_writer(&mut Vec::new()).map_err(Error::into_kind) {
+        Err(ErrorKind::NullNotSupported) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_binary_integers() {
+    // A sixteen byte integer holding u64::MAX
+    let mut buf = b"bplist00\x14\0\0\0\0\0\0\0\0\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x08".to_vec();
+    trailer(&mut buf, 1, 0, 25);
+    let plist = Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(plist, Plist::Integer(u64::max_value().into()));
+
+    let values = vec![Integer::from(42),
+                      Integer::from(300),
+                      Integer::from(-1),
+                      Integer::from(i64::min_value()),
+                      Integer::from(i64::max_value()),
+                      Integer::from(i64::max_value() as u64 + 1),
+                      Integer::from(u64::max_value())];
+    let plist = Plist::Array(values.iter().cloned().map(Plist::Integer).collect());
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    assert_eq!(Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap(), plist);
+
+    assert_eq!(values[2].as_signed(), Some(-1));
+    assert_eq!(values[2].as_unsigned(), None);
+    assert_eq!(values[6].as_signed(), None);
+    assert_eq!(values[6].as_unsigned(), Some(u64::max_value()));
+}
+
+#[test]
+fn test_binary_error_position() {
+    // An array whose second element is an invalid boolean
+    let mut buf = b"bplist00\xA2\x01\x02\x09\x0A".to_vec();
+    buf.extend_from_slice(&[8, 11, 12]);
+    trailer(&mut buf, 3, 0, 13);
+    let error = Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap_err();
+    assert_eq!(error.position(),
+               Some(Position::Binary {
+                   object: Some(2),
+                   offset: 12,
+               }));
+    assert_eq!(error.to_string(),
+               "[1]: Boolean object has an invalid value at object 2 (offset 12)");
+
+    let error = Plist::from_binary_reader(&mut Cursor::new(&b"bplist"[..])).unwrap_err();
+    assert_eq!(error.position(),
+               Some(Position::Binary {
+                   object: None,
+                   offset: 0,
+               }));
+}
+
+#[test]
+fn test_binary_document() {
+    let mut f = File::open("tests/types-binary.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let document = BinaryDocument::new(f).unwrap();
+    let root = document.root();
+    assert_eq!(root.kind().unwrap(), NodeKind::Dict);
+    assert_eq!(root.len().unwrap(), plist.as_dict().map(|d| d.len()));
+    assert_eq!(root.to_plist().unwrap(), plist);
+
+    let array = root.get("Array").unwrap().unwrap();
+    assert_eq!(array.kind().unwrap(), NodeKind::Array);
+    assert_eq!(array.len().unwrap(), plist["Array"].as_array().map(|a| a.len()));
+    assert_eq!(array.index(0).unwrap().unwrap().to_plist().unwrap(), plist["Array"][0]);
+    assert!(array.index(100).unwrap().is_none());
+    assert!(array.get("String").unwrap().is_none());
+
+    let string = root.get("👿").unwrap().unwrap();
+    assert_eq!(string.kind().unwrap(), NodeKind::String);
+    assert_eq!(string.len().unwrap(), None);
+    assert_eq!(string.to_plist().unwrap(), plist["👿"]);
+    assert!(root.get("Missing").unwrap().is_none());
+    assert!(root.index(0).unwrap().is_none());
+}
+
+#[test]
+fn test_binary_document_lazy() {
+    // An array of a string and an invalid boolean, which is only an error
+    // once it is read
+    let mut buf = b"bplist00\xA2\x01\x02\x51a\x0A".to_vec();
+    buf.extend_from_slice(&[8, 11, 13]);
+    trailer(&mut buf, 3, 0, 14);
+
+    let document = BinaryDocument::new(Cursor::new(buf)).unwrap();
+    let root = document.root();
+    assert_eq!(root.index(0).unwrap().unwrap().to_plist().unwrap(),
+               Plist::String("a".to_string()));
+
+    let error = root.index(1).unwrap().unwrap().kind().unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::InvalidBoolean);
+    assert_eq!(error.position(),
+               Some(Position::Binary {
+                   object: Some(2),
+                   offset: 13,
+               }));
+    assert_eq!(root.to_plist().map_err(Error::into_kind),
+               Err(ErrorKind::InvalidBoolean));
+}
+
+#[test]
+fn test_binary_document_error_path() {
+    // A dictionary whose "a" value is an array holding an invalid boolean
+    let mut buf = b"bplist00\xD1\x01\x02\x51a\xA1\x03\x0A".to_vec();
+    buf.extend_from_slice(&[8, 11, 13, 15]);
+    trailer(&mut buf, 4, 0, 16);
+
+    let document = BinaryDocument::new(Cursor::new(&buf[..])).unwrap();
+    let error = document.root().to_plist().unwrap_err();
+    assert_eq!(error.path(), [PathSegment::Key("a".to_string()), PathSegment::Index(0)]);
+    assert_eq!(error.to_string(),
+               "a[0]: Boolean object has an invalid value at object 3 (offset 15)");
+
+    let error = PlistRef::from_slice(&buf).unwrap_err();
+    assert_eq!(error.path(), [PathSegment::Key("a".to_string()), PathSegment::Index(0)]);
+}
+
+#[test]
+fn test_binary_slice() {
+    let mut buf = Vec::new();
+    File::open("tests/types-binary.plist").unwrap().read_to_end(&mut buf).unwrap();
+    let plist = Plist::from_slice(&buf).unwrap();
+    assert_eq!(plist, Plist::from_reader(&mut Cursor::new(&buf)).unwrap());
+
+    let borrowed = PlistRef::from_slice(&buf).unwrap();
+    match borrowed {
+        PlistRef::Dict(ref dict) => {
+            assert!(match dict["String"] {
+                PlistRef::String(Cow::Borrowed("Hello")) => true,
+                _ => false,
+            });
+            assert!(match dict["Data"] {
+                PlistRef::Data(Cow::Borrowed(_)) => true,
+                _ => false,
+            });
+            assert!(match dict["UTF16String"] {
+                PlistRef::String(Cow::Owned(_)) => true,
+                _ => false,
+            });
+            assert!(dict.keys().all(|k| match *k {
+                Cow::Borrowed(_) => true,
+                Cow::Owned(_) => k == "👿",
+            }));
+        }
+        _ => panic!("Root object is not a dictionary"),
+    }
+    assert_eq!(borrowed.into_owned(), plist);
+
+    // Other formats are copied
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    assert_eq!(PlistRef::from_slice(&buf).unwrap().into_owned(), plist);
+}
+
+#[test]
+fn test_binary_slice_invalid() {
+    // A string whose length runs past the end of the slice
+    let mut buf = b"bplist00\x5F\x10\x7F".to_vec();
+    buf.extend_from_slice(&[8]);
+    trailer(&mut buf, 1, 0, 11);
+
+    match PlistRef::from_slice(&buf).map_err(Error::into_kind) {
+        Err(ErrorKind::UnexpectedEof) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
@@ -0,0 +1,85 @@
+extern crate plist;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::io::Cursor;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use plist::{Date, Error, ErrorKind, Plist};
+
+#[test]
+fn test_date_rfc3339() {
+    let format = |t: f64| Date::from_absolute_time(t).to_rfc3339().unwrap();
+    assert_eq!(format(483153508.0), "2016-04-24T01:18:28Z");
+    assert_eq!(format(483153508.5), "2016-04-24T01:18:28.500Z");
+    assert_eq!(format(483153508.123), "2016-04-24T01:18:28.123Z");
+    assert_eq!(format(-0.25), "2000-12-31T23:59:59.750Z");
+    assert_eq!(format(-978307200.0 - 86400.0), "1969-12-31T00:00:00Z");
+    assert_eq!(format(0.1234567891234), "2001-01-01T00:00:00.1234567891234Z");
+
+    let date = Date::from_rfc3339("1969-12-31T00:00:00.125Z").unwrap();
+    assert_eq!(date.absolute_time(), -978307200.0 - 86400.0 + 0.125);
+
+    match Date::from_absolute_time(1e300).to_rfc3339().map_err(Error::into_kind) {
+        Err(ErrorKind::DateOutOfRange) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_date_roundtrip() {
+    // Dates near the reference date are more precise than a nanosecond
+    let dates = [483153508.123456,
+                 -1234.5678,
+                 -978307200.0 - 0.5,
+                 0.1,
+                 1e-9,
+                 0.1234567891234,
+                 -0.1234567891234,
+                 1e-300,
+                 12345.000000000001];
+    let plist = Plist::Array(dates.iter()
+        .map(|&t| Plist::DateTime(Date::from_absolute_time(t)))
+        .collect());
+
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    let binary = Plist::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(binary, plist);
+
+    let mut buf = Vec::new();
+    binary.to_xml_writer(&mut buf).unwrap();
+    let xml = Plist::from_xml_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(xml, plist);
+}
+
+#[test]
+fn test_date_system_time() {
+    let time = UNIX_EPOCH - Duration::new(1, 500_000_000);
+    let date = Date::from(time);
+    assert_eq!(date.absolute_time(), -978307201.5);
+    assert_eq!(SystemTime::try_from(date).unwrap(), time);
+
+    let date = Date::from_absolute_time(-978307200.0 + 0.9999999999);
+    assert_eq!(SystemTime::try_from(date).unwrap(), UNIX_EPOCH + Duration::from_secs(1));
+
+    match SystemTime::try_from(Date::from_absolute_time(std::f64::NAN)).map_err(Error::into_kind) {
+        Err(ErrorKind::DateOutOfRange) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_date_ordering() {
+    let date = |t: f64| Date::from_absolute_time(t);
+    let nan = ::std::f64::NAN;
+
+    let mut dates = vec![date(nan), date(1.0), date(-nan), date(-0.0), date(-1.0)];
+    dates.sort();
+    assert_eq!(dates, [date(-nan), date(-1.0), date(0.0), date(1.0), date(nan)]);
+    assert!(date(0.5) < date(1.0));
+
+    let dates = vec![date(0.0), date(-0.0), date(nan), date(nan)];
+    let set: HashSet<Date> = dates.into_iter().collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&date(0.0)) && set.contains(&date(nan)));
+}
@@ -0,0 +1,69 @@
+extern crate plist;
+
+use std::fs::File;
+use std::io::Cursor;
+use std::time::{Duration, UNIX_EPOCH};
+use plist::{BinaryEventReader, Date, Event, XmlEventReader};
+
+fn expected_events(dict_len: Option<u64>, array_len: Option<u64>) -> Vec<Event> {
+    let date = Date::from(UNIX_EPOCH + Duration::from_secs(1461460708));
+    vec![Event::StartDict(dict_len),
+         Event::Key("Array".to_string()),
+         Event::StartArray(array_len),
+         Event::DateTime(date),
+         Event::EndCollection,
+         Event::Key("Boolean".to_string()),
+         Event::Boolean(true),
+         Event::Key("Data".to_string()),
+         Event::Data(vec![0, 1, 2, 3, 4, 5, 6]),
+         Event::Key("Integer".to_string()),
+         Event::Integer(5.into()),
+         Event::Key("Real".to_string()),
+         Event::Real(0.123456789),
+         Event::Key("String".to_string()),
+         Event::String("Hello".to_string()),
+         Event::Key("UTF16String".to_string()),
+         Event::String("👿".to_string()),
+         Event::Key("👿".to_string()),
+         Event::String("UTF16String".to_string()),
+         Event::EndCollection]
+}
+
+#[test]
+fn test_xml_events() {
+    let f = File::open("tests/types-xml.plist").unwrap();
+    let events: Vec<Event> = XmlEventReader::new(f).map(|e| e.unwrap()).collect();
+    assert_eq!(events, expected_events(None, None));
+}
+
+#[test]
+fn test_binary_events() {
+    let mut f = File::open("tests/types-xml.plist").unwrap();
+    let plist = plist::Plist::from_reader(&mut f).unwrap();
+
+    let mut buf = Vec::new();
+    plist.to_binary_writer(&mut buf).unwrap();
+    let events: Vec<Event> = BinaryEventReader::new(Cursor::new(buf)).map(|e| e.unwrap()).collect();
+    assert_eq!(events, expected_events(Some(8), Some(1)));
+}
+
+#[test]
+fn test_xml_events_invalid() {
+    let xml = "<?xml version=\"1.0\"?><plist><dict><key>a</key><key>b</key></dict></plist>";
+    let mut events = XmlEventReader::new(Cursor::new(xml));
+    assert_eq!(events.next().unwrap().unwrap(), Event::StartDict(None));
+    assert_eq!(events.next().unwrap().unwrap(), Event::Key("a".to_string()));
+    assert!(events.next().unwrap().is_err());
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn test_streaming_large_input() {
+    let f = File::open("benches/large-input-xml.plist").unwrap();
+    let xml_count = XmlEventReader::new(f).map(|e| e.unwrap()).count();
+
+    let f = File::open("benches/large-input-binary.plist").unwrap();
+    let binary_count = BinaryEventReader::new(f).map(|e| e.unwrap()).count();
+
+    assert_eq!(xml_count, binary_count);
+}
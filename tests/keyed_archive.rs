@@ -0,0 +1,158 @@
+extern crate plist;
+
+use std::fs::File;
+use std::io::Cursor;
+use plist::{Date, Error, ErrorKind, Plist};
+use plist::keyed_archive::{ArchivedObject, Class, Field, KeyedArchive, KeyedArchiver, Map, Number,
+                           ObjectRef};
+
+fn string(archive: &KeyedArchive, reference: ObjectRef) -> &str {
+    match *archive.object(reference).unwrap() {
+        ArchivedObject::String(ref s) => s,
+        ref o => panic!("Unexpected object {:?}", o),
+    }
+}
+
+#[test]
+fn test_keyed_archive() {
+    let mut f = File::open("tests/keyed-archive.plist").unwrap();
+    let archive = KeyedArchive::from_reader(&mut f).unwrap();
+    assert_eq!(archive.archiver(), "NSKeyedArchiver");
+    assert_eq!(archive.version(), 100000);
+
+    let entries = match *archive.object(archive.root().unwrap()).unwrap() {
+        ArchivedObject::Dictionary(ref entries) => entries.clone(),
+        ref o => panic!("Unexpected object {:?}", o),
+    };
+    assert_eq!(entries.len(), 2);
+    assert_eq!(string(&archive, entries[0].0), "Items");
+    assert_eq!(string(&archive, entries[1].0), "Person");
+
+    // Mutable subclasses are decoded as their Foundation superclass, and
+    // shared objects are referenced rather than copied
+    let items = match *archive.object(entries[0].1).unwrap() {
+        ArchivedObject::Array(ref items) => items.clone(),
+        ref o => panic!("Unexpected object {:?}", o),
+    };
+    assert_eq!(archive.object(items[0]),
+               Some(&ArchivedObject::Number(Number::Integer(42.into()))));
+    let date = Date::from_absolute_time(482980708.0);
+    assert_eq!(archive.object(items[1]), Some(&ArchivedObject::Date(date)));
+    assert_eq!(items[2], entries[0].0);
+
+    // References from elsewhere may be out of range
+    let mut archiver = KeyedArchiver::new();
+    let mut reference = archiver.nil();
+    while reference.index() < archive.objects().len() {
+        reference = archiver.reserve();
+    }
+    assert_eq!(archive.object(reference), None);
+
+    match *archive.object(entries[1].1).unwrap() {
+        ArchivedObject::Object { ref class, ref fields } => {
+            assert_eq!(class.name, "Person");
+            assert_eq!(class.hierarchy, ["Person", "NSObject"]);
+            assert_eq!(fields["age"], Field::Value(Plist::Integer(30.into())));
+            match fields["name"] {
+                Field::Object(r) => assert_eq!(string(&archive, r), "Ada"),
+                ref f => panic!("Unexpected field {:?}", f),
+            }
+            match fields["parent"] {
+                Field::Object(r) => assert_eq!(archive.object(r), Some(&ArchivedObject::Nil)),
+                ref f => panic!("Unexpected field {:?}", f),
+            }
+        }
+        ref o => panic!("Unexpected object {:?}", o),
+    }
+}
+
+#[test]
+fn test_keyed_archive_invalid() {
+    let mut f = File::open("tests/types-xml.plist").unwrap();
+    match KeyedArchive::from_reader(&mut f).map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidArchive(_)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    let mut f = File::open("tests/keyed-archive.plist").unwrap();
+    let mut plist = Plist::from_reader(&mut f).unwrap();
+    if let Plist::Dict(ref mut dict) = plist {
+        dict.insert("$top".to_string(), Plist::Dict(Some(("root".to_string(), Plist::Uid(13)))
+            .into_iter()
+            .collect()));
+    }
+    match KeyedArchive::from_plist(plist).map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidObjectReference(13)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_keyed_archiver() {
+    let mut archiver = KeyedArchiver::new();
+    let class = Class {
+        name: "Node".to_string(),
+        hierarchy: vec!["Node".to_string(), "NSObject".to_string()],
+    };
+
+    // Two nodes that refer to each other, and share a name
+    let first = archiver.reserve();
+    let name = archiver.encode(ArchivedObject::String("node".to_string())).unwrap();
+    let mut fields = Map::default();
+    fields.insert("name".to_string(), Field::Object(name));
+    fields.insert("next".to_string(), Field::Object(first));
+    fields.insert("weight".to_string(), Field::Value(Plist::Real(0.5)));
+    let second = archiver.encode(ArchivedObject::Object {
+            class: class.clone(),
+            fields: fields.clone(),
+        })
+        .unwrap();
+    fields.insert("next".to_string(), Field::Object(second));
+    archiver.encode_reserved(first,
+                          ArchivedObject::Object {
+                              class: class.clone(),
+                              fields: fields,
+                          })
+        .unwrap();
+
+    let same_name = archiver.encode(ArchivedObject::String("node".to_string())).unwrap();
+    assert_eq!(same_name, name);
+    let uuid = archiver.encode(ArchivedObject::Uuid([7; 16])).unwrap();
+    let null = archiver.encode(ArchivedObject::Null).unwrap();
+    let set = archiver.encode(ArchivedObject::Set(vec![uuid, null])).unwrap();
+    let root = archiver.encode(ArchivedObject::Dictionary(vec![(name, first), (same_name, set)]))
+        .unwrap();
+    archiver.set_top("root", root);
+
+    let mut buf = Vec::new();
+    archiver.into_plist().unwrap().to_binary_writer(&mut buf).unwrap();
+    let archive = KeyedArchive::from_reader(&mut Cursor::new(buf)).unwrap();
+
+    // $null, the nodes and their class, the name, the UUID, NSNull, the set
+    // and dictionary, and their classes
+    assert_eq!(archive.objects().len(), 13);
+    let root = archive.root().unwrap();
+    assert_eq!(archive.object(root),
+               Some(&ArchivedObject::Dictionary(vec![(name, first), (name, set)])));
+    assert_eq!(archive.object(uuid), Some(&ArchivedObject::Uuid([7; 16])));
+    assert_eq!(archive.object(set), Some(&ArchivedObject::Set(vec![uuid, null])));
+    match *archive.object(first).unwrap() {
+        ArchivedObject::Object { ref class, ref fields } => {
+            assert_eq!(class.name, "Node");
+            assert_eq!(fields["next"], Field::Object(second));
+            assert_eq!(fields["weight"], Field::Value(Plist::Real(0.5)));
+        }
+        ref o => panic!("Unexpected object {:?}", o),
+    }
+}
+
+#[test]
+fn test_keyed_archiver_unresolved() {
+    let mut archiver = KeyedArchiver::new();
+    let reserved = archiver.reserve();
+    archiver.set_top("root", reserved);
+    match archiver.into_plist().map_err(Error::into_kind) {
+        Err(ErrorKind::InvalidObjectReference(1)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
@@ -0,0 +1,136 @@
+extern crate plist;
+
+use std::fs::File;
+use std::io::Cursor;
+use plist::{BinaryDocument, Error, ErrorKind, Plist, PlistRef, ReaderConfig, Result};
+
+const FIXTURES: [&'static str; 3] = ["tests/types-binary.plist",
+                                     "tests/types-xml.plist",
+                                     "tests/types-ascii.plist"];
+
+fn read(path: &str, config: &ReaderConfig) -> Result<Plist> {
+    let mut f = File::open(path).unwrap();
+    Plist::from_reader_with_config(&mut f, config)
+}
+
+macro_rules! assert_limit {
+    ($config:expr, $error:pat) => {
+        for path in FIXTURES.iter() {
+            match read(path, &$config).map_err(Error::into_kind) {
+                Err($error) => (),
+                other => panic!("{}: unexpected result {:?}", path, other),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_default_limits() {
+    for path in FIXTURES.iter() {
+        read(path, &ReaderConfig::default()).unwrap();
+    }
+}
+
+#[test]
+fn test_limits() {
+    let config = ReaderConfig::default();
+    assert_limit!(ReaderConfig { max_objects: 10, ..config }, ErrorKind::TooManyObjects);
+    assert_limit!(ReaderConfig { max_string_length: 4, ..config }, ErrorKind::StringTooLong);
+    assert_limit!(ReaderConfig { max_data_length: 6, ..config }, ErrorKind::DataTooLong);
+    assert_limit!(ReaderConfig { max_collection_length: 2, ..config },
+                  ErrorKind::CollectionTooLong);
+    assert_limit!(ReaderConfig { max_depth: 1, ..config }, ErrorKind::MaxDepthExceeded);
+}
+
+#[test]
+fn test_limits_checked_before_allocation() {
+    // A binary plist claiming a string of 2^60 bytes
+    let mut buf = b"bplist00\x5F\x13\x10\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    buf.extend_from_slice(&[8, 0, 0, 0, 0, 0, 0, 1, 1]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 18]);
+
+    let config = ReaderConfig { max_string_length: 1024, ..ReaderConfig::default() };
+    let result = Plist::from_binary_reader_with_config(&mut Cursor::new(&buf[..]), &config);
+    match result.map_err(Error::into_kind) {
+        Err(ErrorKind::StringTooLong) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Without a limit, the length is only trusted as far as the input goes
+    match Plist::from_binary_reader(&mut Cursor::new(&buf[..])).map_err(Error::into_kind) {
+        Err(ErrorKind::UnexpectedEof) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+// A binary plist of `count` arrays, each referencing the next twice, which
+// decodes to 2^(count + 1) - 1 values
+fn shared_references(count: u8) -> Vec<u8> {
+    let mut buf = b"bplist00".to_vec();
+    for i in 0..count {
+        buf.extend_from_slice(&[0xA2, i + 1, i + 1]);
+    }
+    buf.push(0x09);
+
+    let table_offset = buf.len() as u8;
+    for i in 0..count + 1 {
+        buf.push(8 + i * 3);
+    }
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, count + 1]);
+    buf.extend_from_slice(&[0; 8]);
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, table_offset]);
+    buf
+}
+
+#[test]
+fn test_limits_shared_references() {
+    let config = ReaderConfig { max_objects: 1000, ..ReaderConfig::default() };
+    let buf = shared_references(40);
+    let result = Plist::from_reader_with_config(&mut Cursor::new(&buf[..]), &config);
+    match result.map_err(Error::into_kind) {
+        Err(ErrorKind::TooManyObjects) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Every decoded value counts, not just every object in the offset table
+    let buf = shared_references(4);
+    let config = ReaderConfig { max_objects: 30, ..config };
+    assert!(Plist::from_reader_with_config(&mut Cursor::new(&buf[..]), &config).is_err());
+    let config = ReaderConfig { max_objects: 31, ..config };
+    assert!(Plist::from_reader_with_config(&mut Cursor::new(&buf[..]), &config).is_ok());
+}
+
+#[test]
+fn test_limits_shared_references_document() {
+    let config = ReaderConfig { max_objects: 1000, ..ReaderConfig::default() };
+    let document = BinaryDocument::with_config(Cursor::new(shared_references(40)), config).unwrap();
+    match document.root().to_plist().map_err(Error::into_kind) {
+        Err(ErrorKind::TooManyObjects) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Each call to `to_plist` has its own count
+    let config = ReaderConfig { max_objects: 31, ..config };
+    let document = BinaryDocument::with_config(Cursor::new(shared_references(4)), config).unwrap();
+    document.root().to_plist().unwrap();
+    document.root().to_plist().unwrap();
+}
+
+#[test]
+fn test_limits_shared_references_slice() {
+    let config = ReaderConfig { max_objects: 1000, ..ReaderConfig::default() };
+    let buf = shared_references(40);
+    match PlistRef::from_slice_with_config(&buf, &config).map_err(Error::into_kind) {
+        Err(ErrorKind::TooManyObjects) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let buf = shared_references(4);
+    let config = ReaderConfig { max_objects: 30, ..config };
+    assert!(PlistRef::from_slice_with_config(&buf, &config).is_err());
+    let config = ReaderConfig { max_objects: 31, ..config };
+    assert!(PlistRef::from_slice_with_config(&buf, &config).is_ok());
+}
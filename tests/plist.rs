@@ -0,0 +1,98 @@
+#[macro_use]
+extern crate plist;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use plist::{Date, Dictionary, Error, ErrorKind, Plist};
+
+#[test]
+fn test_plist_accessors() {
+    let mut f = File::open("tests/types-xml.plist").unwrap();
+    let mut plist = Plist::from_reader(&mut f).unwrap();
+
+    assert_eq!(plist["String"].as_str(), Some("Hello"));
+    assert_eq!(plist["Integer"].as_i64(), Some(5));
+    assert_eq!(plist["Integer"].as_u64(), Some(5));
+    assert_eq!(plist["Real"].as_f64(), Some(0.123456789));
+    assert_eq!(plist["Boolean"].as_bool(), Some(true));
+    assert_eq!(plist["Data"].as_data(), Some(&[0, 1, 2, 3, 4, 5, 6][..]));
+    assert_eq!(plist["Array"][0].as_date(), Some(Date::from_absolute_time(483153508.0)));
+    assert_eq!(plist["Array"].as_array().map(|a| a.len()), Some(1));
+    assert_eq!(plist.as_dict().map(|d| d.len()), Some(8));
+
+    // Mismatched types, missing keys and out of bounds indices
+    assert_eq!(plist["String"].as_i64(), None);
+    assert_eq!(plist["Missing"]["Key"], Plist::Null);
+    assert_eq!(plist["Array"][1], Plist::Null);
+    assert_eq!(plist[0], Plist::Null);
+
+    plist.as_dict_mut().unwrap()["String"].as_str_mut().unwrap().push_str(", world");
+    assert_eq!(plist["String"].as_str(), Some("Hello, world"));
+    *plist.as_dict_mut().unwrap()["Boolean"].as_bool_mut().unwrap() = false;
+    assert_eq!(plist["Boolean"].as_bool(), Some(false));
+
+    let mut dict = plist.into_dict().unwrap();
+    assert_eq!(dict.remove("String").and_then(Plist::into_string),
+               Some("Hello, world".to_string()));
+    assert_eq!(dict.remove("Data").and_then(Plist::into_array), None);
+}
+
+#[test]
+fn test_plist_conversions() {
+    let mut map = HashMap::new();
+    map.insert("One".to_string(), 1u64);
+    assert_eq!(Plist::from(map.clone()), plist!({ "One": 1 }));
+    assert_eq!(HashMap::<String, u64>::try_from(plist!({ "One": 1 })).unwrap(), map);
+
+    let time = UNIX_EPOCH + Duration::from_millis(1500);
+    assert_eq!(SystemTime::try_from(Plist::from(time)).unwrap(), time);
+    assert_eq!(Vec::<u8>::try_from(Plist::from(vec![1u8, 2])).unwrap(), [1, 2]);
+    assert_eq!(Vec::<i64>::try_from(Plist::from(vec![-1i64, 2])).unwrap(), [-1, 2]);
+    assert_eq!(String::try_from(Plist::from("Hello")).unwrap(), "Hello");
+    assert_eq!(f64::try_from(Plist::from(0.5)).unwrap(), 0.5);
+    assert_eq!(u64::try_from(Plist::from(u64::max_value())).unwrap(), u64::max_value());
+
+    match i64::try_from(Plist::from(u64::max_value())).map_err(Error::into_kind) {
+        Err(ErrorKind::IntegerOutOfRange("i64")) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    match u64::try_from(Plist::from(-1)).map_err(Error::into_kind) {
+        Err(ErrorKind::IntegerOutOfRange("u64")) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    match i64::try_from(Plist::from("1")).map_err(Error::into_kind) {
+        Err(ErrorKind::UnexpectedType("i64")) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    match Vec::<String>::try_from(plist!(["a", true])).map_err(Error::into_kind) {
+        Err(ErrorKind::UnexpectedType("String")) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_plist_macro() {
+    let name = "Ada";
+    let plist = plist!({
+        "Array": [1, -2, [], [true, { "Nested": 0.5 }]],
+        "Empty": {},
+        "Name": name,
+        ("Sum".to_string()): 1 + 2,
+    });
+
+    let mut nested = Dictionary::default();
+    nested.insert("Nested".to_string(), Plist::Real(0.5));
+    let mut dict = Dictionary::default();
+    dict.insert("Array".to_string(),
+                Plist::Array(vec![Plist::Integer(1.into()),
+                                  Plist::Integer((-2).into()),
+                                  Plist::Array(vec![]),
+                                  Plist::Array(vec![Plist::Boolean(true), Plist::Dict(nested)])]));
+    dict.insert("Empty".to_string(), Plist::Dict(Dictionary::default()));
+    dict.insert("Name".to_string(), Plist::String("Ada".to_string()));
+    dict.insert("Sum".to_string(), Plist::Integer(3.into()));
+    assert_eq!(plist, Plist::Dict(dict));
+    assert_eq!(plist!([]), Plist::Array(vec![]));
+}
@@ -0,0 +1,173 @@
+#![cfg(feature = "serde")]
+
+#[macro_use]
+extern crate plist;
+#[macro_use]
+extern crate serde_derive;
+
+use std::fs::File;
+use std::io::Cursor;
+use plist::Date;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Types {
+    array: Vec<Date>,
+    boolean: bool,
+    integer: i64,
+    real: f64,
+    string: String,
+    #[serde(rename = "UTF16String")]
+    utf16_string: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Unit,
+    Newtype(u32),
+    Struct { name: String },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Nested {
+    kinds: Vec<Kind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional: Option<String>,
+    tuple: (u8, String),
+}
+
+#[test]
+fn test_deserialize_types() {
+    let mut xf = File::open("tests/types-xml.plist").unwrap();
+    let mut bf = File::open("tests/types-binary.plist").unwrap();
+
+    let xml: Types = plist::de::from_reader(&mut xf).unwrap();
+    let binary: Types = plist::de::from_reader(&mut bf).unwrap();
+    assert_eq!(xml, binary);
+
+    assert_eq!(xml.array, vec![Date::from_rfc3339("2016-04-24T01:18:28Z").unwrap()]);
+    assert_eq!(xml.boolean, true);
+    assert_eq!(xml.integer, 5);
+    assert_eq!(xml.real, 0.123456789);
+    assert_eq!(xml.string, "Hello");
+    assert_eq!(xml.utf16_string, "👿");
+}
+
+#[test]
+fn test_serde_roundtrip() {
+    let nested = Nested {
+        kinds: vec![Kind::Unit,
+                    Kind::Newtype(7),
+                    Kind::Struct { name: "Name".to_string() }],
+        optional: None,
+        tuple: (1, "one".to_string()),
+    };
+
+    let mut buf = Vec::new();
+    plist::ser::to_binary_writer(&mut buf, &nested).unwrap();
+    let binary: Nested = plist::de::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(binary, nested);
+
+    let mut buf = Vec::new();
+    plist::ser::to_xml_writer(&mut buf, &nested).unwrap();
+    let xml: Nested = plist::de::from_xml_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(xml, nested);
+}
+
+#[test]
+fn test_serialize_date() {
+    let date = Date::from_rfc3339("2016-04-24T01:18:28Z").unwrap();
+    let plist = plist::ser::to_plist(&date).unwrap();
+    assert_eq!(plist, plist::Plist::DateTime(date));
+}
+
+#[test]
+fn test_serde_null() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Optional {
+        values: Vec<Option<i64>>,
+        missing: Option<String>,
+    }
+
+    let optional = Optional {
+        values: vec![Some(1), None],
+        missing: None,
+    };
+
+    // `None` fields are omitted, while null array elements need binary
+    let plist = plist::ser::to_plist(&optional).unwrap();
+    match plist {
+        plist::Plist::Dict(ref dict) => assert!(!dict.contains_key("missing")),
+        _ => panic!("Unexpected plist {:?}", plist),
+    }
+    assert!(plist.to_xml_writer(&mut Vec::new()).is_err());
+
+    let mut buf = Vec::new();
+    plist::ser::to_binary_writer(&mut buf, &optional).unwrap();
+    let binary: Optional = plist::de::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(binary, optional);
+}
+
+#[test]
+fn test_serde_null_fields() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Marker;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Units {
+        unit: (),
+        marker: Marker,
+        some_unit: Option<()>,
+        missing: Option<i64>,
+    }
+
+    let units = Units {
+        unit: (),
+        marker: Marker,
+        some_unit: Some(()),
+        missing: None,
+    };
+
+    // Only `None` fields are omitted
+    let plist = plist::ser::to_plist(&units).unwrap();
+    match plist {
+        plist::Plist::Dict(ref dict) => {
+            let keys: Vec<_> = dict.keys().map(|k| &k[..]).collect();
+            assert_eq!(keys, ["unit", "marker", "some_unit"]);
+            assert!(dict.values().all(|v| *v == plist::Plist::Null));
+        }
+        _ => panic!("Unexpected plist {:?}", plist),
+    }
+
+    let mut buf = Vec::new();
+    plist::ser::to_binary_writer(&mut buf, &units).unwrap();
+    let binary: Units = plist::de::from_binary_reader(&mut Cursor::new(buf)).unwrap();
+
+    // Null reads back as `None`, so `Some(())` cannot round trip
+    assert_eq!(binary, Units { some_unit: None, ..units });
+}
+
+#[test]
+fn test_deserialize_enum_length() {
+    let mut dict = plist::Dictionary::default();
+    dict.insert("Unit".to_string(), plist::Plist::Dict(Default::default()));
+    dict.insert("Newtype".to_string(), plist::Plist::from(7));
+    dict.insert("Struct".to_string(), plist::Plist::Dict(Default::default()));
+
+    let error = plist::de::from_plist::<Kind>(plist::Plist::Dict(dict)).unwrap_err();
+    assert!(error.to_string().contains("invalid length 3"), "{}", error);
+}
+
+#[test]
+fn test_deserialize_tuple_length() {
+    #[derive(Debug, Deserialize)]
+    struct Pair(i64, i64);
+
+    let error = plist::de::from_plist::<(i64, i64)>(plist!([1, 2, 3])).unwrap_err();
+    assert!(error.to_string().contains("invalid length 3"), "{}", error);
+    assert!(plist::de::from_plist::<Pair>(plist!([1, 2, 3])).is_err());
+
+    assert_eq!(plist::de::from_plist::<(i64, i64)>(plist!([1, 2])).unwrap(), (1, 2));
+    let pair = plist::de::from_plist::<Pair>(plist!([1, 2])).unwrap();
+    assert_eq!((pair.0, pair.1), (1, 2));
+}
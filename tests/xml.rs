@@ -0,0 +1,177 @@
+extern crate plist;
+
+use std::error::Error as StdError;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use plist::{Error, ErrorKind, Integer, PathSegment, Plist, Position, WriterConfig};
+
+#[test]
+fn test_xml_plutil_output() {
+    let mut expected = String::new();
+    File::open("tests/types-xml.plist").unwrap().read_to_string(&mut expected).unwrap();
+
+    let mut f = File::open("tests/types-binary.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    // plutil sorts keys, whereas the binary fixture is in a different order
+    let config = WriterConfig { sort_keys: true };
+    let mut buf = Vec::new();
+    plist.to_xml_writer_with_config(&mut buf, &config).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap().trim_end(), expected.trim_end());
+
+    // By default, the keys keep the order of the binary fixture
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    assert!(String::from_utf8(buf).unwrap().trim_end() != expected.trim_end());
+}
+
+#[test]
+fn test_xml_key_order() {
+    let mut f = File::open("tests/types-binary.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    let decoded = Plist::from_xml_reader(&mut Cursor::new(buf)).unwrap();
+
+    let keys = |p: &Plist| match *p {
+        Plist::Dict(ref d) => d.keys().cloned().collect::<Vec<_>>(),
+        _ => panic!("Root object is not a dictionary"),
+    };
+    let expected = ["Array", "String", "Data", "Boolean", "UTF16String", "Integer", "Real", "👿"];
+    assert_eq!(keys(&decoded), expected);
+    assert_eq!(keys(&decoded), keys(&plist));
+}
+
+#[test]
+fn test_xml_roundtrip() {
+    let mut f = File::open("benches/large-input-xml.plist").unwrap();
+    let plist = Plist::from_reader(&mut f).unwrap();
+
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    let decoded = Plist::from_xml_reader(&mut Cursor::new(buf)).unwrap();
+    assert_eq!(plist, decoded);
+}
+
+#[test]
+fn test_xml_data_wrapping() {
+    let plist = Plist::Array(vec![Plist::Data(vec![0; 60]), Plist::String("<&>".to_string())]);
+
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    let output = String::from_utf8(buf).unwrap();
+    let data = format!("\t<data>\n\t{}\n\t{}\n\t</data>\n", "A".repeat(68), "A".repeat(12));
+    assert!(output.contains(&data));
+    assert!(output.contains("<string>&lt;&amp;&gt;</string>"));
+}
+
+#[test]
+fn test_xml_uid() {
+    let plist = Plist::Array(vec![Plist::Uid(7)]);
+
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    let output = String::from_utf8(buf).unwrap();
+    let uid = "\t<dict>\n\t\t<key>CF$UID</key>\n\t\t<integer>7</integer>\n\t</dict>\n";
+    assert!(output.contains(uid));
+
+    let decoded = Plist::from_xml_reader(&mut Cursor::new(output.as_bytes())).unwrap();
+    assert_eq!(decoded, plist);
+}
+
+#[test]
+fn test_xml_integers() {
+    let parse = |s: &str| {
+        let xml = format!("<?xml version=\"1.0\"?><plist><integer>{}</integer></plist>", s);
+        Plist::from_xml_reader(&mut Cursor::new(xml.as_bytes()))
+    };
+    let integer = |s: &str| match parse(s).unwrap() {
+        Plist::Integer(i) => i,
+        p => panic!("Unexpected plist {:?}", p),
+    };
+
+    assert_eq!(integer(" 42\n"), Integer::from(42));
+    assert_eq!(integer("+7"), Integer::from(7));
+    assert_eq!(integer("-42"), Integer::from(-42));
+    assert_eq!(integer("0x1F"), Integer::from(31));
+    assert_eq!(integer("-0x10"), Integer::from(-16));
+    assert_eq!(integer("18446744073709551615"), Integer::from(u64::max_value()));
+    assert_eq!(integer("-9223372036854775808"), Integer::from(i64::min_value()));
+
+    let invalid = ["", "++1", "-+1", "0x", "1.5", "18446744073709551616", "-9223372036854775809"];
+    for invalid in &invalid {
+        assert!(parse(invalid).is_err(), "{:?} should not parse", invalid);
+    }
+
+    let plist = Plist::Integer(u64::max_value().into());
+    let mut buf = Vec::new();
+    plist.to_xml_writer(&mut buf).unwrap();
+    assert_eq!(Plist::from_xml_reader(&mut Cursor::new(buf)).unwrap(), plist);
+}
+
+#[test]
+fn test_xml_error_position() {
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<plist version=\"1.0\">
+<dict>
+\t<key>Integer</key>
+\t<integer>five</integer>
+</dict>
+</plist>";
+    let error = Plist::from_xml_reader(&mut Cursor::new(xml)).unwrap_err();
+    assert_eq!(error.position(),
+               Some(Position::Text {
+                   line: 5,
+                   column: 15,
+               }));
+    assert_eq!(error.to_string(),
+               "Integer: Integer is invalid at line 5, column 15");
+    assert_eq!(error.kind(), &ErrorKind::InvalidInteger);
+    assert_eq!(error.source().unwrap().to_string(), "invalid digit found in string");
+}
+
+#[test]
+fn test_xml_error_path() {
+    let xml = "<plist><dict>
+<key>CFBundleDocumentTypes</key>
+<array>
+<dict/>
+<dict/>
+<dict><key>LSItemContentTypes</key><array><integer>x</integer></array></dict>
+</array>
+</dict></plist>";
+    let error = Plist::from_xml_reader(&mut Cursor::new(xml)).unwrap_err();
+    assert_eq!(error.path(),
+               [PathSegment::Key("CFBundleDocumentTypes".to_string()),
+                PathSegment::Index(2),
+                PathSegment::Key("LSItemContentTypes".to_string()),
+                PathSegment::Index(0)]);
+    assert!(error.to_string()
+        .starts_with("CFBundleDocumentTypes[2].LSItemContentTypes[0]: Integer is invalid"));
+}
+
+#[test]
+fn test_xml_error_send_sync() {
+    fn assert_send_sync<T: Send + Sync + 'static>(_: T) {}
+
+    let error = Plist::from_xml_reader(&mut Cursor::new("<plist><dict>")).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::InvalidXml);
+    assert!(error.source().is_some());
+    assert_send_sync(error);
+    assert_send_sync(Error::from(ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn test_xml_error_path_quoted() {
+    let xml = "<plist><dict><key>Entitlements</key><dict>
+<key>com.apple.security.app-sandbox</key><array><integer>x</integer></array>
+</dict></dict></plist>";
+    let error = Plist::from_xml_reader(&mut Cursor::new(xml)).unwrap_err();
+    assert!(error.to_string()
+        .starts_with("Entitlements[\"com.apple.security.app-sandbox\"][0]: Integer is invalid"));
+
+    assert_eq!(PathSegment::Key("".to_string()).to_string(), "[\"\"]");
+    assert_eq!(PathSegment::Key("a[\"b\"]".to_string()).to_string(), "[\"a[\\\"b\\\"]\"]");
+    assert_eq!(PathSegment::Key("👿".to_string()).to_string(), "👿");
+}